@@ -0,0 +1,211 @@
+//! Sustained full-auto fire with pooled bullets against the old spawn-a-new-mesh-per-bullet path.
+//!
+//! `cargo bench --bench projectile_pool`
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy::render::mesh::MeshPlugin;
+use bevy::time::TimeUpdateStrategy;
+use fps::{DamageType, LaunchProjectile, ProjectilePlugin};
+
+/// Counts every allocation, so the two paths can be compared by more than frame time
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Eight players holding the trigger on 600 rpm rifles
+const SHOTS_PER_SECOND: f32 = 80.0;
+const WARMUP_FRAMES: u32 = 120;
+const FRAMES: u32 = 1200;
+const FRAME_TIME: f64 = 1.0 / 60.0;
+
+fn main() {
+    let naive = run(false);
+    let pooled = run(true);
+    println!(
+        "{:<8} {:>10} {:>10} {:>14} {:>8} {:>8} {:>9}",
+        "", "mean", "p99", "allocs/frame", "meshes", "mats", "entities"
+    );
+    for (name, report) in [("naive", naive), ("pooled", pooled)] {
+        println!(
+            "{name:<8} {:>8.3}ms {:>8.3}ms {:>14.1} {:>8} {:>8} {:>9}",
+            report.mean.as_secs_f64() * 1000.0,
+            report.p99.as_secs_f64() * 1000.0,
+            report.allocations as f64 / FRAMES as f64,
+            report.meshes,
+            report.materials,
+            report.entities,
+        );
+    }
+}
+
+struct Report {
+    mean: Duration,
+    p99: Duration,
+    allocations: usize,
+    meshes: usize,
+    materials: usize,
+    entities: u32,
+}
+
+fn run(pooled: bool) -> Report {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        AssetPlugin::default(),
+        MeshPlugin,
+        PhysicsPlugins::default(),
+    ))
+    .init_asset::<StandardMaterial>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        FRAME_TIME,
+    )));
+    if pooled {
+        app.add_plugins(ProjectilePlugin)
+            .add_systems(FixedUpdate, fire_pooled);
+    } else {
+        app.add_systems(FixedUpdate, (fire_naive, despawn_naive));
+    }
+    // a floor for the bullets that drop to bounce off
+    app.world_mut().spawn((
+        RigidBody::Static,
+        Collider::cuboid(400.0, 1.0, 400.0),
+        Transform::from_xyz(0.0, -0.5, 0.0),
+    ));
+    app.finish();
+    app.cleanup();
+
+    for _ in 0..WARMUP_FRAMES {
+        app.update();
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut frames = (0..FRAMES)
+        .map(|_| {
+            let start = Instant::now();
+            app.update();
+            start.elapsed()
+        })
+        .collect::<Vec<_>>();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    frames.sort();
+
+    let world = app.world();
+    Report {
+        mean: frames.iter().sum::<Duration>() / FRAMES,
+        p99: frames[(FRAMES as usize * 99) / 100],
+        allocations,
+        meshes: world.resource::<Assets<Mesh>>().len(),
+        materials: world.resource::<Assets<StandardMaterial>>().len(),
+        entities: world.entities().len(),
+    }
+}
+
+/// Shots due this tick, carrying the remainder over
+fn shots_due(time: &Time, owed: &mut f32) -> u32 {
+    *owed += SHOTS_PER_SECOND * time.delta_secs();
+    let shots = owed.floor();
+    *owed -= shots;
+    shots as u32
+}
+
+/// Spread out and slightly down, some hit the floor and some fly off
+fn shot_direction(index: u32) -> Dir3 {
+    let angle = index as f32 * 0.618;
+    Dir3::new(Vec3::new(
+        angle.sin(),
+        -0.05 * (index % 3) as f32,
+        angle.cos(),
+    ))
+    .unwrap_or(Dir3::NEG_Z)
+}
+
+fn fire_pooled(
+    time: Res<Time>,
+    mut owed: Local<f32>,
+    mut count: Local<u32>,
+    mut launches: EventWriter<LaunchProjectile>,
+) {
+    for _ in 0..shots_due(&time, &mut owed) {
+        *count += 1;
+        launches.write(LaunchProjectile {
+            shooter: Entity::PLACEHOLDER,
+            origin: Vec3::Y,
+            direction: shot_direction(*count),
+            speed: 400.0,
+            damage: 25.0,
+            damage_type: DamageType::Normal,
+            shot: *count,
+            light: None,
+        });
+    }
+}
+
+#[derive(Component)]
+struct NaiveBullet;
+
+/// What firing did before the pool, a new mesh and material for every bullet
+fn fire_naive(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut owed: Local<f32>,
+    mut count: Local<u32>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for _ in 0..shots_due(&time, &mut owed) {
+        *count += 1;
+        let direction = shot_direction(*count);
+        commands.spawn((
+            NaiveBullet,
+            Collider::sphere(0.001),
+            Mesh3d(meshes.add(Sphere::new(0.01))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(0.8, 0.7, 0.6),
+                emissive: LinearRgba::new(0.4, 0.3, 0.2, 0.1),
+                perceptual_roughness: 0.1,
+                metallic: 0.8,
+                ..default()
+            })),
+            RigidBody::Dynamic,
+            Mass(0.001),
+            SpeculativeMargin::ZERO,
+            Transform::from_translation(Vec3::Y + direction * 1.0),
+            LinearVelocity(direction * 400.0),
+            SweptCcd::default(),
+            Friction::new(0.1),
+            Restitution::new(0.6),
+            LinearDamping(0.01),
+        ));
+    }
+}
+
+/// The old rule, only bullets slower than 50 m/s were removed
+fn despawn_naive(
+    mut commands: Commands,
+    bullets: Query<(Entity, &LinearVelocity), With<NaiveBullet>>,
+) {
+    for (entity, velocity) in &bullets {
+        if velocity.length() < 50.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -14,8 +14,6 @@ use bevy::{
 use fps::*;
 use iyes_perf_ui::prelude::PerfUiDefaultEntries;
 
-const SPAWN_POINT: Vec3 = Vec3::new(0.0, 1.625, 0.0);
-
 fn main() {
     App::new()
         .insert_resource(AmbientLight {
@@ -33,6 +31,28 @@ fn main() {
         .add_plugins(PhysicsPlugins::new(FixedPostUpdate))
         .add_plugins(GoldenUI)
         .add_plugins(GunPlayPlugin)
+        .add_plugins(ProjectilePlugin)
+        .add_plugins(BallisticsPlugin)
+        .add_plugins(WeaponPlugin)
+        .add_plugins(DecoratePlugin::default())
+        .add_plugins(WeaponStatePlugin)
+        .add_plugins(ReloadPlugin)
+        .add_plugins(WeaponSlotsPlugin)
+        .add_plugins(RecoilPlugin)
+        .add_plugins(DamagePlugin)
+        .add_plugins(ImpactPlugin)
+        .add_plugins(MeleePlugin)
+        .add_plugins(ExplosivePlugin)
+        .add_plugins(AimPlugin)
+        .add_plugins(UpgradePlugin)
+        .add_plugins(GlLightPlugin)
+        .add_plugins(TriggerPlugin)
+        .add_plugins(RespawnPlugin)
+        .add_plugins(SplitScreenPlugin)
+        .add_plugins(GravityZonePlugin)
+        .add_plugins(VehiclePlugin)
+        .add_plugins(InteractPlugin)
+        .add_plugins(CarryPlugin)
         //.add_plugins(PhysicsDebugPlugin::default())
         .add_plugins(GoldenControllerPlugin)
         .add_plugins(bevy_framepace::FramepacePlugin)
@@ -44,7 +64,6 @@ fn main() {
                 manage_cursor,
                 scene_colliders,
                 //    display_text,
-                respawn,
                 rotate_this,
             ),
         )
@@ -54,17 +73,20 @@ fn main() {
 #[derive(Component)]
 struct RotateThis {
     rotated: bool,
+    pivot: Vec3,
 }
 
 fn setup(
     mut commands: Commands,
     mut window: Query<&mut Window>,
     assets: Res<AssetServer>,
+    decorate_weapons: Res<DecorateWeapons>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     let mut window = window.single_mut().unwrap();
     window.title = String::from("Minimal FPS Controller Example");
+    let spawn_point = Vec3::new(0.0, 1.625, 0.0);
     commands.spawn(PerfUiDefaultEntries::default());
 
     let e = commands
@@ -88,73 +110,157 @@ fn setup(
     let height = 1.0;
     let radius = 0.4;
     let mass = 80.0;
-    let logical_entity = commands
-        .spawn((
-            Collider::capsule(radius, height),
-            // A capsule can be used but is NOT recommended
-            // If you use it, you have to make sure each segment point is
-            // equidistant from the translation of the player transform
-            // Collider::capsule(0.5, height),
-            Friction {
-                dynamic_coefficient: 0.0,
-                static_coefficient: 0.0,
-                combine_rule: CoefficientCombine::Min,
-            },
-            Restitution {
-                coefficient: 0.0,
-                combine_rule: CoefficientCombine::Min,
-            },
-            LinearVelocity::ZERO,
-            SpeculativeMargin::ZERO,
-            RigidBody::Dynamic,
-            Sleeping,
-            LockedAxes::ROTATION_LOCKED,
-            Mass(mass),
-            GravityScale(1.0),
-            Transform::from_translation(SPAWN_POINT),
-            LogicalPlayer,
-            LinearDamping(0.5),
-        ))
-        .insert(CameraConfig {
-            height_offset: -0.2,
-        })
-        .insert(PlayerControllerBundle {
-            controller: GoldenController {
-                radius,
-                height,
-                mass,
-
-                ..default()
-            },
+    // LOCAL_PLAYERS=2..4 for split-screen, the first player is on keyboard and mouse,
+    // the rest pick up gamepads as they get connected
+    let local_players = std::env::var("LOCAL_PLAYERS")
+        .ok()
+        .and_then(|players| players.parse::<u32>().ok())
+        .unwrap_or(1)
+        .clamp(1, 4);
+    for index in 0..local_players {
+        let player_spawn = spawn_point + Vec3::X * 2.0 * index as f32;
+        let input_device = if index == 0 {
+            InputDevice::KeyboardMouse
+        } else {
+            InputDevice::Gamepad(None)
+        };
+        // reserve rounds, shared by the pack's weapons and the RON ones
+        let mut inventory = PlayerInventory {
+            items: [
+                ("rifleammo".to_string(), 90),
+                ("ninemilammo".to_string(), 68),
+                ("shotgunammo".to_string(), 24),
+                ("slugammo".to_string(), 10),
+                ("xbowammo".to_string(), 12),
+                ("itemmodkit".to_string(), 3),
+                ("pipeammo".to_string(), 3),
+            ]
+            .into(),
             ..default()
-        })
-        .insert(PlayerStuffBundle::default())
-        .id();
+        };
+        let class = decorate_weapons.player("Scavenger");
+        for (item, amount) in class.iter().flat_map(|class| &class.start_items) {
+            inventory.give(item, *amount, None);
+        }
+        // the pack's slots, with the RON weapons on 0
+        let mut slots = decorate_weapons.slots("Scavenger").unwrap_or_default();
+        slots.slots.resize_with(10, Vec::new);
+        slots.slots[0].extend(
+            ["pistol", "shotgun", "rifle", "musket"]
+                .map(|name| assets.load(format!("weapons/{name}.weapon.ron"))),
+        );
+        let logical_entity = commands
+            .spawn((
+                Collider::capsule(radius, height),
+                // A capsule can be used but is NOT recommended
+                // If you use it, you have to make sure each segment point is
+                // equidistant from the translation of the player transform
+                // Collider::capsule(0.5, height),
+                Friction {
+                    dynamic_coefficient: 0.0,
+                    static_coefficient: 0.0,
+                    combine_rule: CoefficientCombine::Min,
+                },
+                Restitution {
+                    coefficient: 0.0,
+                    combine_rule: CoefficientCombine::Min,
+                },
+                LinearVelocity::ZERO,
+                SpeculativeMargin::ZERO,
+                RigidBody::Dynamic,
+                Sleeping,
+                LockedAxes::ROTATION_LOCKED,
+                Mass(mass),
+                GravityScale(1.0),
+                Transform::from_translation(player_spawn),
+                LogicalPlayer,
+                input_device,
+                LinearDamping(0.5),
+            ))
+            .insert(CameraConfig {
+                height_offset: -0.2,
+            })
+            .insert(PlayerControllerBundle {
+                controller: GoldenController {
+                    radius,
+                    height,
+                    mass,
 
-    let e = commands
-        .spawn((
-            Camera3d::default(),
-            Camera {
-                hdr: true, // 1. HDR is required for bloom
-                //      clear_color: ClearColorConfig::Custom(Color::BLACK),
+                    ..default()
+                },
                 ..default()
-            },
-            Tonemapping::TonyMcMapface, // 2. Using a tonemapper that desaturates to white is recommended
-            Bloom::NATURAL,             // 3. Enable bloom for the camera
-            Projection::Perspective(PerspectiveProjection {
-                fov: TAU / 5.0,
+            })
+            .insert(PlayerStuffBundle {
+                inventory,
                 ..default()
-            }),
-            Exposure::SUNLIGHT,
-            RenderPlayer { logical_entity },
-        ))
-        .id();
-    println!("camera ent, {:#?}", e);
+            })
+            .insert(Respawnable::default())
+            .insert(slots)
+            .insert(Weapon::new(
+                decorate_weapons
+                    .get("FAL")
+                    .unwrap_or_else(|| assets.load("weapons/rifle.weapon.ron")),
+            ))
+            .id();
+
+        let e = commands
+            .spawn((
+                Camera3d::default(),
+                Camera {
+                    hdr: true, // 1. HDR is required for bloom
+                    //      clear_color: ClearColorConfig::Custom(Color::BLACK),
+                    ..default()
+                },
+                Tonemapping::TonyMcMapface, // 2. Using a tonemapper that desaturates to white is recommended
+                Bloom::NATURAL,             // 3. Enable bloom for the camera
+                Projection::Perspective(PerspectiveProjection {
+                    fov: TAU / 5.0,
+                    ..default()
+                }),
+                Exposure::SUNLIGHT,
+                RenderPlayer { logical_entity },
+                LocalPlayer { index },
+            ))
+            .id();
+        println!("camera ent, {:#?}", e);
+    }
     commands.insert_resource(MainScene {
         handle: assets.load("playground3.glb"),
         is_loaded: false,
     });
 
+    commands.spawn((
+        SpawnPoint::default(),
+        Transform::from_translation(spawn_point).looking_to(Vec3::NEG_Z, Vec3::Y),
+    ));
+
+    // Anything falling off the map ends up in here
+    commands.spawn((
+        KillVolume,
+        Collider::cuboid(2000.0, 10.0, 2000.0),
+        Transform::from_xyz(0.0, -55.0, 0.0),
+    ));
+
+    // A motorcycle, walk up to it and press F to ride
+    commands.spawn((
+        Vehicle::default(),
+        Collider::cuboid(0.4, 0.6, 1.8),
+        Mass(200.0),
+        Mesh3d(meshes.add(Cuboid::new(0.4, 0.6, 1.8))),
+        MeshMaterial3d(materials.add(Color::srgb(0.2, 0.2, 0.25))),
+        Transform::from_translation(spawn_point + Vec3::new(-4.0, 0.5, -4.0)),
+    ));
+
+    // A workbench, press F at it to put a modkit on the held weapon
+    commands.spawn((
+        Workbench,
+        RigidBody::Static,
+        Collider::cuboid(1.6, 0.9, 0.8),
+        Mesh3d(meshes.add(Cuboid::new(1.6, 0.9, 0.8))),
+        MeshMaterial3d(materials.add(Color::srgb(0.45, 0.3, 0.2))),
+        Transform::from_translation(spawn_point + Vec3::new(4.0, 0.45, -4.0)),
+    ));
+
     // A cube to move around
     commands.spawn((
         RigidBody::Dynamic,
@@ -162,7 +268,7 @@ fn setup(
         Mesh3d(meshes.add(Cuboid::default())),
         Mass(40.0),
         MeshMaterial3d(materials.add(Color::srgb(0.8, 0.7, 0.6))),
-        Transform::from_translation(SPAWN_POINT + Vec3::new(10.0, 10.0, 10.0)),
+        Transform::from_translation(spawn_point + Vec3::new(10.0, 10.0, 10.0)),
         Friction {
             dynamic_coefficient: 0.9,
             static_coefficient: 0.9,
@@ -175,7 +281,7 @@ fn rotate_this(mut query: Query<(&mut Transform, &mut RotateThis)>) {
     for (mut transform, mut rotate_this) in &mut query {
         if !rotate_this.rotated {
             transform.rotate_around(
-                SPAWN_POINT + Vec3::new(2.75, -1.5, 3.0),
+                rotate_this.pivot,
                 Quat::from_euler(
                     EulerRot::XYZ,
                     45_f32.to_radians(),
@@ -188,17 +294,6 @@ fn rotate_this(mut query: Query<(&mut Transform, &mut RotateThis)>) {
     }
 }
 
-fn respawn(mut query: Query<(&mut Transform, &mut LinearVelocity)>) {
-    for (mut transform, mut velocity) in &mut query {
-        if transform.translation.y > -50.0 {
-            continue;
-        }
-
-        velocity.0 = Vec3::ZERO;
-        transform.translation = SPAWN_POINT;
-    }
-}
-
 #[derive(Resource)]
 struct MainScene {
     handle: Handle<Gltf>,
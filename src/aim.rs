@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+use crate::{
+    Accuracy, Carrier, DT, GoldenControllerButtons, GoldenControllerInput,
+    GoldenControllerMutables, RenderPlayer, WEAPON_TOP, Weapon, WeaponDef, WeaponStateMachine,
+    shoot_bullet,
+};
+
+/// Aiming down the sights: the view zooms in, look sensitivity drops with it
+/// and the spread tightens, following the weapon's [`Sights`](crate::Sights)
+pub struct AimPlugin;
+
+impl Plugin for AimPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, aim_down_sights.before(shoot_bullet))
+            .add_systems(Update, zoom_cameras);
+    }
+}
+
+/// How far into the sights a logical player is
+#[derive(Component)]
+pub struct Aim {
+    /// 0 from the hip, 1 fully aimed
+    pub blend: f32,
+    /// How many times the view is magnified right now
+    pub zoom: f32,
+    /// Share of the hip sensitivity currently applied to the controller
+    sensitivity_scale: f32,
+}
+
+impl Default for Aim {
+    fn default() -> Self {
+        Self {
+            blend: 0.0,
+            zoom: 1.0,
+            sensitivity_scale: 1.0,
+        }
+    }
+}
+
+/// The field of view a camera had before any zooming
+#[derive(Component)]
+struct HipFov(f32);
+
+/// System: bring the sights up while aim is held and the weapon is ready, and tighten the spread
+#[allow(clippy::type_complexity)]
+fn aim_down_sights(
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut query: Query<(
+        &GoldenControllerInput,
+        &Weapon,
+        &WeaponStateMachine,
+        &mut Aim,
+        &mut Accuracy,
+        Option<&Carrier>,
+    )>,
+) {
+    for (input, weapon, machine, mut aim, mut accuracy, carrier) in &mut query {
+        let Some(sights) = weapon_defs.get(&weapon.def).map(|def| &def.sights) else {
+            continue;
+        };
+        // nothing to aim while reloading, switching or carrying something
+        let aiming = input.aim
+            && weapon.reload.is_none()
+            && machine.y <= WEAPON_TOP
+            && carrier.is_none_or(|carrier| carrier.held.is_none());
+        let target = if aiming { 1.0 } else { 0.0 };
+        let step = DT / sights.time.max(DT);
+        aim.blend += (target - aim.blend).clamp(-step, step);
+        aim.zoom = 1.0 + (sights.zoom.max(1.0) - 1.0) * aim.blend;
+        accuracy.aim = 1.0 + (sights.spread - 1.0) * aim.blend;
+    }
+}
+
+/// System: narrow each camera to its player's zoom, scaling their look sensitivity to match
+fn zoom_cameras(
+    mut commands: Commands,
+    mut cameras: Query<(Entity, &RenderPlayer, &mut Projection, Option<&HipFov>)>,
+    mut players: Query<(
+        &mut Aim,
+        &mut GoldenControllerMutables,
+        Option<&mut GoldenControllerButtons>,
+    )>,
+) {
+    for (camera, render_player, mut projection, hip_fov) in &mut cameras {
+        let Projection::Perspective(perspective) = projection.bypass_change_detection() else {
+            continue;
+        };
+        let Some(HipFov(hip_fov)) = hip_fov else {
+            commands.entity(camera).insert(HipFov(perspective.fov));
+            continue;
+        };
+        let Ok((mut aim, mut mutables, buttons)) = players.get_mut(render_player.logical_entity)
+        else {
+            continue;
+        };
+        // zooming divides the size of the view, not the angle
+        let fov = 2.0 * ((hip_fov / 2.0).tan() / aim.zoom).atan();
+        if perspective.fov != fov {
+            perspective.fov = fov;
+            projection.set_changed();
+        }
+
+        // rescaled rather than overwritten, so sensitivity changes made while aiming stick
+        let scale = 1.0 / aim.zoom;
+        if scale != aim.sensitivity_scale {
+            let change = scale / aim.sensitivity_scale;
+            mutables.sensitivity *= change;
+            if let Some(mut buttons) = buttons {
+                buttons.look_speed *= change;
+            }
+            aim.sensitivity_scale = scale;
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{DamageType, Damageable, ShotHit, SurfaceMaterial, shoot_bullet};
+
+/// Ballistic hitscan: rounds marched through the world with drop and drag,
+/// punching through thin surfaces, with tracers along the same path
+pub struct BallisticsPlugin;
+
+impl Plugin for BallisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FireBallistic>()
+            .add_event::<ShotHit>()
+            .add_systems(FixedUpdate, trace_ballistic_shots.after(shoot_bullet))
+            .add_systems(Update, draw_tracers);
+    }
+}
+
+/// Seconds of flight per ray cast, small enough that the drop bends the path smoothly
+const STEP: f32 = 1.0 / 120.0;
+
+/// Thickest surface a round looks through, in metres, anything deeper stops it
+const MAX_THICKNESS: f32 = 1.0;
+
+/// Share of the damage a round keeps after each surface it goes through
+const PENETRATION_DAMAGE: f32 = 0.7;
+
+/// Metres a round is moved past a surface it went through, so it doesn't hit it again
+const EXIT_OFFSET: f32 = 0.01;
+
+/// Metres of tracer drawn behind the round
+const TRACER_LENGTH: f32 = 4.0;
+
+/// Marches a ballistic round on the next fixed tick
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FireBallistic {
+    /// The logical player who fired
+    pub shooter: Entity,
+    pub origin: Vec3,
+    pub direction: Dir3,
+    /// Muzzle velocity in m/s
+    pub speed: f32,
+    /// Quadratic drag, the round slows by `drag * speed²` m/s every second
+    pub drag: f32,
+    /// Metres travelled before the round is given up on
+    pub max_range: f32,
+    /// Metres of stone the round can go through, other surfaces by their resistance
+    pub penetration: f32,
+    pub tracer: bool,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    /// See [`ShotHit::shot`]
+    pub shot: u32,
+}
+
+/// A streak of light following a round's path
+#[derive(Component)]
+struct Tracer {
+    /// Where the round went, the last point is where it stopped
+    points: Vec<Vec3>,
+    /// m/s
+    speed: f32,
+    /// Metres along the path the head of the tracer is
+    traveled: f32,
+}
+
+/// System: march each round step by step, writing a [`ShotHit`] for every surface it enters
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn trace_ballistic_shots(
+    mut commands: Commands,
+    gravity: Res<Gravity>,
+    spatial_query: SpatialQuery,
+    mut shots: EventReader<FireBallistic>,
+    mut shot_hits: EventWriter<ShotHit>,
+    surfaces: Query<&SurfaceMaterial>,
+    damageables: Query<(), With<Damageable>>,
+    sensors: Query<(), With<Sensor>>,
+) {
+    for shot in shots.read() {
+        let filter = SpatialQueryFilter::default().with_excluded_entities([shot.shooter]);
+        // triggers aren't in the way
+        let solid = |entity: Entity| !sensors.contains(entity);
+        let mut position = shot.origin;
+        let mut velocity = shot.direction * shot.speed;
+        let mut traveled = 0.0;
+        let mut penetration = shot.penetration;
+        let mut damage = shot.damage;
+        let mut points = vec![position];
+
+        while traveled < shot.max_range {
+            velocity += (gravity.0 - shot.drag * velocity.length() * velocity) * STEP;
+            let step = velocity * STEP;
+            let Ok(direction) = Dir3::new(step) else {
+                break;
+            };
+            let length = step.length().min(shot.max_range - traveled);
+            let Some(hit) = spatial_query
+                .cast_ray_predicate(position, direction, length, true, &filter, &solid)
+            else {
+                position += direction * length;
+                traveled += length;
+                points.push(position);
+                continue;
+            };
+
+            let entry = position + direction * hit.distance;
+            traveled += hit.distance;
+            points.push(entry);
+            shot_hits.write(ShotHit {
+                shooter: shot.shooter,
+                entity: hit.entity,
+                point: entry,
+                normal: hit.normal,
+                direction,
+                distance: traveled,
+                damage,
+                damage_type: shot.damage_type,
+                shot: shot.shot,
+            });
+
+            // cast back from past the surface to find its far side
+            let only_hit = |entity: Entity| entity == hit.entity;
+            let thickness = spatial_query
+                .cast_ray_predicate(
+                    entry + direction * MAX_THICKNESS,
+                    -direction,
+                    MAX_THICKNESS,
+                    true,
+                    &SpatialQueryFilter::default(),
+                    &only_hit,
+                )
+                // starting inside means it's thicker than that
+                .filter(|exit| exit.distance > 0.0)
+                .map(|exit| MAX_THICKNESS - exit.distance);
+            let surface = SurfaceMaterial::of(hit.entity, &surfaces, &damageables);
+            let Some(thickness) = thickness
+                .filter(|thickness| thickness * surface.penetration_resistance() <= penetration)
+            else {
+                break;
+            };
+            penetration -= thickness * surface.penetration_resistance();
+            damage *= PENETRATION_DAMAGE;
+            position = entry + direction * (thickness + EXIT_OFFSET);
+            traveled += thickness + EXIT_OFFSET;
+            points.push(position);
+        }
+
+        if shot.tracer {
+            commands.spawn(Tracer {
+                points,
+                speed: shot.speed,
+                traveled: 0.0,
+            });
+        }
+    }
+}
+
+/// System: move tracers along their paths and remove them once they've reached the end
+fn draw_tracers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+    mut tracers: Query<(Entity, &mut Tracer)>,
+) {
+    for (entity, mut tracer) in &mut tracers {
+        tracer.traveled += tracer.speed * time.delta_secs();
+        let head = tracer.traveled;
+        let tail = head - TRACER_LENGTH;
+        let mut start = 0.0;
+        let mut drawn = false;
+        for segment in tracer.points.windows(2) {
+            let length = segment[0].distance(segment[1]);
+            let end = start + length;
+            if end > tail && start < head && length > 0.0 {
+                let from = segment[0].lerp(segment[1], ((tail - start) / length).max(0.0));
+                let to = segment[0].lerp(segment[1], ((head - start) / length).min(1.0));
+                gizmos.line(from, to, Color::srgb(4.0, 3.0, 1.5));
+                drawn = true;
+            }
+            start = end;
+        }
+        if !drawn && tail > 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{GoldenControllerInput, InteractionFocus, LogicalPlayer, RenderPlayer};
+
+pub struct CarryPlugin;
+
+impl Plugin for CarryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, grab_and_throw)
+            .add_systems(FixedUpdate, carry_held);
+    }
+}
+
+/// Lets a logical player pick up dynamic bodies with the use key.
+/// Fire throws, use drops, holding the rotate key turns the object with the mouse.
+#[derive(Component)]
+pub struct Carrier {
+    pub max_mass: f32,
+    pub reach: f32,
+    pub hold_distance: f32,
+    /// How hard the object is pulled to the hold point, 1/s
+    pub follow_stiffness: f32,
+    pub max_follow_speed: f32,
+    /// Drop the object once it is this far from the hold point, it is stuck on something
+    pub break_distance: f32,
+    pub throw_impulse: f32,
+    pub held: Option<Entity>,
+    /// Held object rotation relative to the camera yaw
+    pub held_rotation: Quat,
+    /// The fire press that threw the last object is still down, weapons wait for it to come up
+    pub throwing: bool,
+}
+
+impl Default for Carrier {
+    fn default() -> Self {
+        Self {
+            max_mass: 50.0,
+            reach: 2.5,
+            hold_distance: 1.5,
+            follow_stiffness: 15.0,
+            max_follow_speed: 10.0,
+            break_distance: 1.5,
+            throw_impulse: 400.0,
+            held: None,
+            held_rotation: Quat::IDENTITY,
+            throwing: false,
+        }
+    }
+}
+
+impl Carrier {
+    /// Fire is taken by the carried object, either to throw it or because it just was
+    pub fn blocks_fire(&self) -> bool {
+        self.held.is_some() || self.throwing
+    }
+}
+
+fn grab_and_throw(
+    mut commands: Commands,
+    spatial_query_pipeline: Res<SpatialQueryPipeline>,
+    cameras: Query<(&RenderPlayer, &GlobalTransform)>,
+    mut players: Query<
+        (
+            &mut Carrier,
+            &GoldenControllerInput,
+            Option<&InteractionFocus>,
+        ),
+        With<LogicalPlayer>,
+    >,
+    bodies: Query<(&RigidBody, &ComputedMass, &Transform)>,
+    collider_bodies: Query<&ColliderOf>,
+) {
+    for (render_player, camera_transform) in &cameras {
+        let Ok((mut carrier, input, focus)) = players.get_mut(render_player.logical_entity) else {
+            continue;
+        };
+
+        if let Some(held) = carrier.held {
+            if input.fire {
+                commands.entity(held).insert(ExternalImpulse::new(
+                    camera_transform.forward() * carrier.throw_impulse,
+                ));
+                carrier.throwing = true;
+            }
+            if input.fire || input.interact {
+                release(&mut commands, &mut carrier);
+            }
+            continue;
+        }
+
+        // interactables get the use key first
+        if !input.interact || focus.is_some_and(|focus| focus.target.is_some()) {
+            continue;
+        }
+        let filter =
+            SpatialQueryFilter::default().with_excluded_entities([render_player.logical_entity]);
+        let Some(hit) = spatial_query_pipeline.cast_ray(
+            camera_transform.translation(),
+            camera_transform.forward(),
+            carrier.reach,
+            true,
+            &filter,
+        ) else {
+            continue;
+        };
+        let body = collider_bodies
+            .get(hit.entity)
+            .map_or(hit.entity, |collider_of| collider_of.body);
+        let Ok((rigid_body, mass, transform)) = bodies.get(body) else {
+            continue;
+        };
+        if !rigid_body.is_dynamic() || mass.value() > carrier.max_mass {
+            continue;
+        }
+
+        let (yaw, _, _) = camera_transform.rotation().to_euler(EulerRot::YXZ);
+        carrier.held = Some(body);
+        carrier.held_rotation = Quat::from_rotation_y(yaw).inverse() * transform.rotation;
+        // a held object can never push the player, so it can't be used to boost
+        commands.entity(body).insert(Dominance(-1));
+    }
+}
+
+fn release(commands: &mut Commands, carrier: &mut Carrier) {
+    let Some(held) = carrier.held.take() else {
+        return;
+    };
+    if let Ok(mut entity) = commands.get_entity(held) {
+        entity.remove::<Dominance>();
+    }
+}
+
+pub fn carry_held(
+    mut commands: Commands,
+    spatial_query_pipeline: Res<SpatialQueryPipeline>,
+    cameras: Query<(&RenderPlayer, &GlobalTransform)>,
+    mut players: Query<(&mut Carrier, &mut GoldenControllerInput), With<LogicalPlayer>>,
+    mut bodies: Query<(&Transform, &mut LinearVelocity, &mut AngularVelocity)>,
+) {
+    for (render_player, camera_transform) in &cameras {
+        let Ok((mut carrier, mut input)) = players.get_mut(render_player.logical_entity) else {
+            continue;
+        };
+        if !input.fire {
+            carrier.throwing = false;
+        }
+        // the rotate key only takes over the mouse while something is held
+        input.look_locked = carrier.held.is_some() && input.rotate;
+
+        let Some(held) = carrier.held else {
+            continue;
+        };
+        let Ok((transform, mut velocity, mut angular_velocity)) = bodies.get_mut(held) else {
+            // despawned while held
+            carrier.held = None;
+            continue;
+        };
+
+        let eye = camera_transform.translation();
+        let target = eye + camera_transform.forward() * carrier.hold_distance;
+        let offset = target - transform.translation;
+
+        // too far from where it should be, or something got between us and it
+        let filter = SpatialQueryFilter::default()
+            .with_excluded_entities([render_player.logical_entity, held]);
+        let to_object = transform.translation - eye;
+        let obstructed = Dir3::new(to_object).is_ok_and(|direction| {
+            spatial_query_pipeline
+                .cast_ray(eye, direction, to_object.length(), true, &filter)
+                .is_some()
+        });
+        if offset.length() > carrier.break_distance || obstructed {
+            release(&mut commands, &mut carrier);
+            continue;
+        }
+
+        if input.look_locked {
+            // held rotation lives in the camera yaw frame, so these are camera up and right
+            let delta = input.rotate_delta;
+            carrier.held_rotation = Quat::from_axis_angle(Vec3::Y, -delta.x)
+                * Quat::from_axis_angle(Vec3::X, -delta.y)
+                * carrier.held_rotation;
+        }
+        input.rotate_delta = Vec2::ZERO;
+
+        // velocity follow instead of a joint, gravity and collisions just get overridden
+        velocity.0 = (offset * carrier.follow_stiffness).clamp_length_max(carrier.max_follow_speed);
+        let (yaw, _, _) = camera_transform.rotation().to_euler(EulerRot::YXZ);
+        let target_rotation = Quat::from_rotation_y(yaw) * carrier.held_rotation;
+        let mut rotation_error = target_rotation * transform.rotation.inverse();
+        if rotation_error.w < 0.0 {
+            // take the short way around
+            rotation_error = -rotation_error;
+        }
+        angular_velocity.0 = rotation_error.to_scaled_axis() * carrier.follow_stiffness;
+    }
+}
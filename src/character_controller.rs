@@ -6,6 +6,8 @@ use bevy::input::mouse::MouseScrollUnit;
 use bevy::input::mouse::MouseWheel;
 use bevy::{input::mouse::MouseMotion, prelude::*};
 
+use crate::{Carrier, GravityField, GravityZone};
+
 pub struct GoldenControllerPlugin;
 pub static FPS: f64 = 120.0;
 pub static DT: f32 = 1.0 / FPS as f32;
@@ -14,9 +16,11 @@ impl Plugin for GoldenControllerPlugin {
         use bevy::input::{gamepad, keyboard, mouse, touch};
 
         app.insert_resource(Time::<Fixed>::from_hz(FPS))
+            .add_event::<ControllerImpulse>()
             .add_systems(
                 PreUpdate,
                 (
+                    assign_gamepads,
                     fps_controller_input,
                     fps_controller_look,
                     fps_controller_render,
@@ -32,7 +36,9 @@ impl Plugin for GoldenControllerPlugin {
             .add_systems(
                 FixedUpdate,
                 (
+                    fps_controller_gravity,
                     fps_controller_spatial_hitter,
+                    fps_controller_impulse,
                     fps_controller_move,
                     fps_controller_crouch,
                     fps_controller_lean,
@@ -46,14 +52,21 @@ impl Plugin for GoldenControllerPlugin {
 pub struct PlayerControllerBundle {
     pub controller: GoldenController,
     pub keys: GoldenControllerKeys,
+    pub buttons: GoldenControllerButtons,
     pub mutables: GoldenControllerMutables,
     pub input: GoldenControllerInput,
     pub spatial_hits: GoldenControllerSpatialHits,
+    pub gravity: GoldenControllerGravity,
 }
 
 #[derive(Component)]
 pub struct LogicalPlayer;
 
+/// While present the controller leaves this player alone, input is still read.
+/// Vehicles use it to take over the player.
+#[derive(Component)]
+pub struct ControllerSuspended;
+
 #[derive(Component)]
 pub struct RenderPlayer {
     pub logical_entity: Entity,
@@ -74,6 +87,25 @@ pub struct GoldenControllerInput {
     pub lean: f32, // -1.0 left, +1.0 right
     pub lean_degree_mod: f32,
     pub crouch_degree_mod: f32,
+    pub fire: bool,
+    /// True only on the frame the use key went down, so read it from `Update`
+    pub interact: bool,
+    /// Set when the reload key goes down and kept while it's held, taken by whoever starts the reload
+    pub reload: bool,
+    /// Quick melee, like the pack's `+user1` bootknife
+    pub melee: bool,
+    /// Held to wind up a grenade throw, it flies on release
+    pub throw: bool,
+    /// Held to aim down the sights
+    pub aim: bool,
+    /// Weapon switch asked for, taken by whoever does the switching
+    pub weapon_select: Option<WeaponSelect>,
+    /// Next fire mode asked for, taken by whoever does the switching
+    pub switch_fire_mode: bool,
+    pub rotate: bool,
+    /// Set by whoever wants the mouse for itself, look input then piles up in `rotate_delta`
+    pub look_locked: bool,
+    pub rotate_delta: Vec2,
 }
 
 impl Default for GoldenControllerInput {
@@ -87,10 +119,31 @@ impl Default for GoldenControllerInput {
             lean: 0.0,
             lean_degree_mod: 0.0,
             crouch_degree_mod: 1.0,
+            fire: false,
+            interact: false,
+            reload: false,
+            melee: false,
+            throw: false,
+            aim: false,
+            weapon_select: None,
+            switch_fire_mode: false,
+            rotate: false,
+            look_locked: false,
+            rotate_delta: Vec2::ZERO,
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeaponSelect {
+    /// Number keys, 0 to 9
+    Slot(u8),
+    Next,
+    Previous,
+    /// Back to the weapon held before
+    Last,
+}
+
 #[derive(Component)]
 pub struct GoldenController {
     pub radius: f32,
@@ -125,6 +178,11 @@ pub struct GoldenController {
 
     pub lean_side_impulse: f32,
     pub leaning_speed: f32,
+
+    /// Radians per second the body turns to line up with a new gravity direction
+    pub up_align_speed: f32,
+    pub thruster_acceleration: f32,
+    pub thruster_roll_speed: f32,
 }
 
 impl Default for GoldenController {
@@ -167,6 +225,12 @@ impl Default for GoldenController {
             //how much to move horizontally while leaning
             lean_side_impulse: 650.0,
 
+            //how fast you turn to stand on a wall after gravity changes
+            up_align_speed: 4.0,
+            //zero-g movement, lean keys roll
+            thruster_acceleration: 8.0,
+            thruster_roll_speed: 1.5,
+
             enable_input: true,
         }
     }
@@ -182,6 +246,17 @@ pub struct GoldenControllerKeys {
     pub key_lean_right: KeyCode,
     pub key_crouch: KeyCode,
     pub key_jump: KeyCode,
+    pub key_use: KeyCode,
+    pub key_rotate: KeyCode,
+    pub key_reload: KeyCode,
+    pub key_melee: KeyCode,
+    pub key_throw: KeyCode,
+    pub key_last_weapon: KeyCode,
+    pub key_fire_mode: KeyCode,
+    /// Indexed by weapon slot
+    pub keys_weapon_slot: [KeyCode; 10],
+    pub button_fire: MouseButton,
+    pub button_aim: MouseButton,
 }
 
 impl Default for GoldenControllerKeys {
@@ -195,10 +270,84 @@ impl Default for GoldenControllerKeys {
             key_lean_right: KeyCode::KeyE,
             key_crouch: KeyCode::ShiftLeft,
             key_jump: KeyCode::Space,
+            key_use: KeyCode::KeyF,
+            key_rotate: KeyCode::AltLeft,
+            key_reload: KeyCode::KeyR,
+            key_melee: KeyCode::KeyV,
+            key_throw: KeyCode::KeyG,
+            key_last_weapon: KeyCode::KeyX,
+            key_fire_mode: KeyCode::KeyB,
+            keys_weapon_slot: [
+                KeyCode::Digit0,
+                KeyCode::Digit1,
+                KeyCode::Digit2,
+                KeyCode::Digit3,
+                KeyCode::Digit4,
+                KeyCode::Digit5,
+                KeyCode::Digit6,
+                KeyCode::Digit7,
+                KeyCode::Digit8,
+                KeyCode::Digit9,
+            ],
+            button_fire: MouseButton::Left,
+            button_aim: MouseButton::Right,
         }
     }
 }
 
+#[derive(Component)]
+pub struct GoldenControllerButtons {
+    pub lean_left: GamepadButton,
+    pub lean_right: GamepadButton,
+    pub crouch: GamepadButton,
+    pub jump: GamepadButton,
+    pub fire: GamepadButton,
+    pub interact: GamepadButton,
+    pub rotate: GamepadButton,
+    pub reload: GamepadButton,
+    pub melee: GamepadButton,
+    pub throw: GamepadButton,
+    pub aim: GamepadButton,
+    pub next_weapon: GamepadButton,
+    pub previous_weapon: GamepadButton,
+    pub last_weapon: GamepadButton,
+    pub fire_mode: GamepadButton,
+    /// Radians per second at full right stick deflection
+    pub look_speed: f32,
+}
+
+impl Default for GoldenControllerButtons {
+    fn default() -> Self {
+        Self {
+            lean_left: GamepadButton::LeftTrigger,
+            lean_right: GamepadButton::RightTrigger,
+            crouch: GamepadButton::East,
+            jump: GamepadButton::South,
+            fire: GamepadButton::RightTrigger2,
+            interact: GamepadButton::West,
+            rotate: GamepadButton::LeftThumb,
+            reload: GamepadButton::North,
+            melee: GamepadButton::RightThumb,
+            throw: GamepadButton::DPadDown,
+            aim: GamepadButton::LeftTrigger2,
+            next_weapon: GamepadButton::DPadRight,
+            previous_weapon: GamepadButton::DPadLeft,
+            last_weapon: GamepadButton::DPadUp,
+            fire_mode: GamepadButton::Select,
+            look_speed: 3.0,
+        }
+    }
+}
+
+/// Where a local player's input comes from, players without one use keyboard and mouse.
+/// `Gamepad(None)` gets the next gamepad that isn't claimed by another player.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad(Option<Entity>),
+}
+
 #[derive(Component)]
 pub struct GoldenControllerMutables {
     pub pitch: f32,
@@ -206,6 +355,65 @@ pub struct GoldenControllerMutables {
     pub lean_degree: f32,
     pub sensitivity: f32,
     pub crouch_degree: f32,
+    /// Seconds left during which ground friction and the spring-damper are suspended,
+    /// set by [`ControllerImpulse`] so the player can actually leave the ground
+    pub impulse_lift: f32,
+    /// Rotates world Y onto the local up, yaw and pitch are measured in this frame
+    pub up_rotation: Quat,
+    /// Gravity acceleration the player currently feels
+    pub gravity: Vec3,
+    pub zero_g: bool,
+}
+
+/// Which gravity field a player follows, gravity zones it stands in override `field`
+#[derive(Component, Default)]
+pub struct GoldenControllerGravity {
+    pub field: GravityField,
+    /// Gravity zones the player is inside, the most recently entered last
+    pub zones: Vec<Entity>,
+}
+
+/// How a [`ControllerImpulse`] is combined with the current velocity
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ImpulseMode {
+    /// Adds the velocity change on top of the current velocity (knockback, explosions)
+    #[default]
+    Add,
+    /// Replaces the velocity along the impulse direction, so jump pads always launch
+    /// the same height no matter how fast the player was falling
+    Launch,
+}
+
+/// The sanctioned way to push a player, applied by the controller on the next fixed tick.
+/// Pushing through `ExternalImpulse` directly gets eaten by the spring-damper while grounded.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ControllerImpulse {
+    pub entity: Entity,
+    /// Velocity change in m/s, independent of the player mass
+    pub velocity: Vec3,
+    pub mode: ImpulseMode,
+    /// How long ground friction and the spring-damper hold are lifted
+    pub lift_time: f32,
+}
+
+impl ControllerImpulse {
+    pub fn new(entity: Entity, velocity: Vec3) -> Self {
+        Self {
+            entity,
+            velocity,
+            mode: ImpulseMode::Add,
+            lift_time: 0.2,
+        }
+    }
+
+    pub fn launch(entity: Entity, velocity: Vec3) -> Self {
+        Self {
+            entity,
+            velocity,
+            mode: ImpulseMode::Launch,
+            lift_time: 0.3,
+        }
+    }
 }
 #[derive(Component, Default)]
 pub struct GoldenControllerSpatialHits {
@@ -226,6 +434,10 @@ impl Default for GoldenControllerMutables {
             pitch: 0.0,
             yaw: 0.0,
             sensitivity: 0.001,
+            impulse_lift: 0.0,
+            up_rotation: Quat::IDENTITY,
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            zero_g: false,
         }
     }
 }
@@ -238,7 +450,7 @@ impl Default for GoldenControllerMutables {
 // ╚══════╝ ╚═════╝  ╚═════╝ ╚═╝ ╚═════╝
 
 // Used as padding by camera pitching (up/down) to avoid spooky math problems
-const ANGLE_EPSILON: f32 = 0.001953125;
+pub(crate) const ANGLE_EPSILON: f32 = 0.001953125;
 const CALC_EPSILON: f32 = 0.01;
 
 const SLIGHT_SCALE_DOWN: f32 = 0.9;
@@ -255,22 +467,43 @@ pub fn fps_controller_move(
             &mut ExternalImpulse,
             &mut LinearDamping,
         ),
-        With<LogicalPlayer>,
+        (With<LogicalPlayer>, Without<ControllerSuspended>),
     >,
-    gravity: Res<Gravity>,
 ) {
     for (
         input,
         controller,
         spatial_hits,
-        controller_mutables,
+        mut controller_mutables,
         mut velocity,
         mut external_force,
         mut damping,
     ) in query.iter_mut()
     {
+        // an impulse is lifting us off the ground, so skip friction and the height spring
+        let lifted = controller_mutables.impulse_lift > 0.0;
+        controller_mutables.impulse_lift = (controller_mutables.impulse_lift - DT).max(0.0);
+
+        let basis = local_basis(&controller_mutables, input.yaw);
+        let up = controller_mutables.up_rotation * Vec3::Y;
+
+        if controller_mutables.zero_g {
+            // 6-DOF thrusters, move where you look, jump and crouch go up and down
+            damping.0 = controller.air_damp;
+            let view = basis * Quat::from_axis_angle(Vec3::X, input.pitch);
+            let vertical = (input.jump as i8 - input.crouch as i8) as f32;
+            let thrust = view * Vec3::new(input.movement.x, vertical, -input.movement.z);
+            external_force.apply_impulse(
+                thrust.normalize_or_zero()
+                    * controller.thruster_acceleration
+                    * DT
+                    * controller.mass,
+            );
+            continue;
+        }
+
         let speeds = Vec3::new(controller.side_speed, 0.0, controller.forward_speed);
-        let mut move_to_world = Mat3::from_axis_angle(Vec3::Y, input.yaw);
+        let mut move_to_world = Mat3::from_quat(basis);
         move_to_world.z_axis *= -1.0; // Forward is -Z
         let mut wish_direction = move_to_world * (input.movement * speeds);
         let mut wish_speed = wish_direction.length();
@@ -291,10 +524,10 @@ pub fn fps_controller_move(
             controller.air_acceleration,
             velocity.0,
         );
-        if spatial_hits.bottom_down {
+        if spatial_hits.bottom_down && !lifted {
             // check if player is on walkable slope
-            let has_traction = Vec3::dot(spatial_hits.bottom_hit_normal, Vec3::Y)
-                > controller.traction_normal_cutoff;
+            let has_traction =
+                Vec3::dot(spatial_hits.bottom_hit_normal, up) > controller.traction_normal_cutoff;
             if has_traction {
                 damping.0 = controller.air_damp * 10.0;
 
@@ -310,7 +543,7 @@ pub fn fps_controller_move(
 
                 // PURE VERTICAL SPRING–DAMPER (no slope support)
                 if !input.jump {
-                    // spring–damper height control (vertical only)
+                    // spring–damper height control (along local up only)
                     let current_height = spatial_hits.bottom_down_distance;
                     let target_height = (controller.grounded_distance * 0.8)
                         / (1.0 + controller_mutables.crouch_degree);
@@ -323,8 +556,8 @@ pub fn fps_controller_move(
                     // damping coefficient (critical damping = 2 * m * omega)
                     let c = 2.0 * controller.mass * omega * 0.4; // 0.8 = slightly underdamped
 
-                    // velocity along Y
-                    let vel_y = velocity.0.y;
+                    // velocity along up
+                    let vel_y = velocity.0.dot(up);
 
                     // spring force (scalar)
                     let f_spring = k * height_error;
@@ -333,13 +566,13 @@ pub fn fps_controller_move(
                     let f_damp = -c * vel_y;
 
                     // include gravity
-                    let gravity_force = controller.mass * gravity.0.y;
+                    let gravity_force = controller.mass * controller_mutables.gravity.dot(up);
 
-                    // total force along Y
+                    // total force along up
                     let f_total_y = f_spring + f_damp - gravity_force;
 
                     // impulse this frame
-                    let spring_damper_impulse = Vec3::Y * (f_total_y * DT);
+                    let spring_damper_impulse = up * (f_total_y * DT);
                     external_force.apply_impulse(spring_damper_impulse);
                 }
 
@@ -347,16 +580,16 @@ pub fn fps_controller_move(
                 if !input.jump && input.movement.length_squared() < 0.1 {
                     damping.0 = controller.air_damp * 30.0;
                     //  Fixes wobbly velocity
-                    if velocity.0.z.abs() < CALC_EPSILON {
-                        velocity.0.z = 0.0;
-                    }
-                    if velocity.0.x.abs() < CALC_EPSILON {
-                        velocity.0.x = 0.0;
+                    for axis in [basis * Vec3::X, basis * Vec3::Z] {
+                        let along = velocity.0.dot(axis);
+                        if along.abs() < CALC_EPSILON {
+                            velocity.0 -= axis * along;
+                        }
                     }
                 }
 
                 //this has to be tuned to prevent double jumps
-                if input.jump && velocity.0.y < 1.0 {
+                if input.jump && velocity.0.dot(up) < 1.0 {
                     {
                         let linear_velocity = velocity.0;
                         let normal_force =
@@ -364,11 +597,7 @@ pub fn fps_controller_move(
                                 * spatial_hits.bottom_hit_normal;
                         velocity.0 -= normal_force;
                     }
-                    let jump_force = Vec3 {
-                        x: 0.0,
-                        y: controller.jump_force,
-                        z: 0.0,
-                    } * controller.mass;
+                    let jump_force = up * controller.jump_force * controller.mass;
                     external_force.apply_impulse(jump_force);
                 }
             }
@@ -377,6 +606,85 @@ pub fn fps_controller_move(
     }
 }
 
+#[allow(clippy::type_complexity)]
+pub fn fps_controller_gravity(
+    world_gravity: Res<Gravity>,
+    zones: Query<&GravityZone>,
+    mut query: Query<
+        (
+            &GoldenControllerInput,
+            &GoldenController,
+            &GoldenControllerGravity,
+            &mut GoldenControllerMutables,
+            &mut LinearVelocity,
+            &Transform,
+            Option<&GravityScale>,
+        ),
+        (With<LogicalPlayer>, Without<ControllerSuspended>),
+    >,
+) {
+    for (input, controller, gravity, mut controller_mutables, mut velocity, transform, scale) in
+        query.iter_mut()
+    {
+        let field = gravity
+            .zones
+            .iter()
+            .rev()
+            .find_map(|zone| zones.get(*zone).ok())
+            .map_or(gravity.field, |zone| zone.field);
+        let acceleration = field.acceleration(transform.translation, world_gravity.0);
+
+        // avian already applies the world gravity, only add the difference
+        let applied = world_gravity.0 * scale.map_or(1.0, |scale| scale.0);
+        velocity.0 += (acceleration - applied) * DT;
+        controller_mutables.gravity = acceleration;
+        controller_mutables.zero_g = field == GravityField::ZeroG;
+
+        let up_rotation = controller_mutables.up_rotation;
+        if controller_mutables.zero_g {
+            // nothing to align to, lean keys roll around the view direction instead
+            let forward = local_basis(&controller_mutables, input.yaw) * Vec3::NEG_Z;
+            let roll = -input.lean * controller.thruster_roll_speed * DT;
+            controller_mutables.up_rotation =
+                (Quat::from_axis_angle(forward, roll) * up_rotation).normalize();
+        } else if let Ok(target_up) = Dir3::new(-acceleration) {
+            // turn towards the new up a bit every tick instead of snapping the camera
+            let current_up = up_rotation * Vec3::Y;
+            let angle = current_up.angle_between(*target_up);
+            let step = (controller.up_align_speed * DT / angle.max(f32::EPSILON)).min(1.0);
+            let turn = Quat::IDENTITY.slerp(Quat::from_rotation_arc(current_up, *target_up), step);
+            controller_mutables.up_rotation = (turn * up_rotation).normalize();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn fps_controller_impulse(
+    mut impulses: EventReader<ControllerImpulse>,
+    mut query: Query<
+        (&mut GoldenControllerMutables, &mut LinearVelocity),
+        (With<LogicalPlayer>, Without<ControllerSuspended>),
+    >,
+) {
+    for impulse in impulses.read() {
+        let Ok((mut controller_mutables, mut velocity)) = query.get_mut(impulse.entity) else {
+            continue;
+        };
+        match impulse.mode {
+            ImpulseMode::Add => velocity.0 += impulse.velocity,
+            ImpulseMode::Launch => {
+                if let Ok(direction) = Dir3::new(impulse.velocity) {
+                    // drop whatever speed we had along the launch direction and replace it
+                    let along = velocity.0.dot(*direction);
+                    velocity.0 += direction * (impulse.velocity.length() - along);
+                }
+            }
+        }
+        controller_mutables.impulse_lift = controller_mutables.impulse_lift.max(impulse.lift_time);
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn fps_controller_spatial_hitter(
     spatial_query_pipeline: Res<SpatialQueryPipeline>,
     mut query: Query<
@@ -384,20 +692,39 @@ pub fn fps_controller_spatial_hitter(
             Entity,
             &GoldenControllerInput,
             &GoldenController,
+            &GoldenControllerMutables,
             &mut GoldenControllerSpatialHits,
             &Collider,
             &mut Transform,
+            Option<&Carrier>,
         ),
-        With<LogicalPlayer>,
+        (With<LogicalPlayer>, Without<ControllerSuspended>),
     >,
 ) {
-    for (entity, input, controller, mut spatial_hits, collider, transform) in query.iter_mut() {
+    for (
+        entity,
+        input,
+        controller,
+        controller_mutables,
+        mut spatial_hits,
+        collider,
+        transform,
+        carrier,
+    ) in query.iter_mut()
+    {
         // Shape cast downwards to find ground
         // Better than a ray cast as it handles when you are near the edge of a surface
-        let filter = SpatialQueryFilter::default().with_excluded_entities([entity]);
+        // what we carry is never ground, or it could lift us up
+        let held = carrier.and_then(|carrier| carrier.held);
+        let filter =
+            SpatialQueryFilter::default().with_excluded_entities([entity].into_iter().chain(held));
+
+        let basis = local_basis(controller_mutables, input.yaw);
+        let up_rotation = controller_mutables.up_rotation;
+        let up = up_rotation * Dir3::Y;
 
         let speeds = Vec3::new(controller.side_speed, 0.0, controller.forward_speed);
-        let mut move_to_world = Mat3::from_axis_angle(Vec3::Y, input.yaw);
+        let mut move_to_world = Mat3::from_quat(basis);
         move_to_world.z_axis *= -1.0; // Forward is -Z
         let mut wish_direction = move_to_world * (input.movement * speeds);
         let wish_speed = wish_direction.length();
@@ -406,12 +733,12 @@ pub fn fps_controller_spatial_hitter(
             wish_direction /= wish_speed; // Effectively normalize, avoid length computation twice
         }
         let foot_shape = Collider::cylinder(controller.radius * 0.9, 0.01);
-        let feet_origin = transform.translation - collider_y_offset(&collider) * 0.95;
+        let feet_origin = transform.translation - up_rotation * collider_y_offset(collider) * 0.95;
         let bottom_down_hit = spatial_query_pipeline.cast_shape(
             &foot_shape,
             feet_origin,
-            Quat::IDENTITY,
-            -Dir3::Y,
+            up_rotation,
+            -up,
             &ShapeCastConfig::from_max_distance(
                 controller.grounded_distance * 1.1, //+ controller.lean_degree.abs() / 20.0 hack to stay grounded while leaning
             ),
@@ -433,9 +760,9 @@ pub fn fps_controller_spatial_hitter(
         // the top hit should be at least the stair height so that the player isnt translated inside a roof
         let top_up_hit = spatial_query_pipeline.cast_shape(
             &scaled_collider_laterally(&collider, 0.99),
-            transform.translation + Vec3::new(0.0, controller.height, 0.0),
-            Quat::IDENTITY,
-            Dir3::Y,
+            transform.translation + up * controller.height,
+            up_rotation,
+            up,
             &ShapeCastConfig::from_max_distance(controller.grounded_distance),
             &filter,
         );
@@ -445,8 +772,7 @@ pub fn fps_controller_spatial_hitter(
             spatial_hits.top_up = false;
         }
 
-        let yaw_rotation = Quat::from_euler(EulerRot::YXZ, input.yaw, 0.0, 0.0);
-        let right_dir = yaw_rotation * Vec3::X; // world-space right
+        let right_dir = basis * Vec3::X; // world-space right
 
         let probe_origin = transform.translation;
         let probe_distance = 1.0;
@@ -496,7 +822,7 @@ pub fn fps_controller_lean(
             &mut ExternalImpulse,
             &mut Transform,
         ),
-        With<LogicalPlayer>,
+        (With<LogicalPlayer>, Without<ControllerSuspended>),
     >,
 ) {
     for (
@@ -509,13 +835,17 @@ pub fn fps_controller_lean(
     ) in query.iter_mut()
     {
         /* Leaning */
-        let yaw_rotation = Quat::from_euler(EulerRot::YXZ, input.yaw, 0.0, 0.0);
-        let right_dir = yaw_rotation * Vec3::X; // world-space right
+        let basis = local_basis(&controller_mutables, input.yaw);
+        let right_dir = basis * Vec3::X; // world-space right
 
         let lean_step = controller.leaning_speed * DT;
 
-        // Desired lean from input
-        let mut target_lean = input.lean;
+        // Desired lean from input, in zero-g the lean keys roll instead
+        let mut target_lean = if controller_mutables.zero_g {
+            0.0
+        } else {
+            input.lean
+        };
 
         // Block intentional lean into wall
         if spatial_hits.right_wall_dist.0 && (target_lean > 0.0) {
@@ -557,7 +887,7 @@ pub fn fps_controller_lean(
         // Rotate to show visual lean
         let lean_amount = controller_mutables.lean_degree * controller.lean_max;
         let lean_rotation = Quat::from_axis_angle(Vec3::Z, -lean_amount);
-        transform.rotation = (yaw_rotation * lean_rotation).normalize();
+        transform.rotation = (basis * lean_rotation).normalize();
     }
 }
 pub fn fps_controller_crouch(
@@ -569,7 +899,7 @@ pub fn fps_controller_crouch(
             &mut GoldenControllerMutables,
             &mut Collider,
         ),
-        With<LogicalPlayer>,
+        (With<LogicalPlayer>, Without<ControllerSuspended>),
     >,
 ) {
     for (input, controller, spatial_hits, mut controller_mutables, mut collider) in query.iter_mut()
@@ -577,7 +907,7 @@ pub fn fps_controller_crouch(
         /* Crouching */
 
         // Target crouch state: 1 = crouch, 0 = stand
-        let target_crouch = if input.crouch {
+        let target_crouch = if input.crouch && !controller_mutables.zero_g {
             input.crouch_degree_mod
         } else {
             0.0
@@ -632,48 +962,146 @@ pub fn fps_controller_crouch(
  *
  */
 
+pub fn assign_gamepads(gamepads: Query<Entity, With<Gamepad>>, mut query: Query<&mut InputDevice>) {
+    let claimed: Vec<Entity> = query
+        .iter()
+        .filter_map(|device| match device {
+            InputDevice::Gamepad(gamepad) => *gamepad,
+            InputDevice::KeyboardMouse => None,
+        })
+        .filter(|gamepad| gamepads.contains(*gamepad))
+        .collect();
+    let mut free = gamepads.iter().filter(|gamepad| !claimed.contains(gamepad));
+
+    for mut device in query.iter_mut() {
+        if let InputDevice::Gamepad(gamepad) = *device {
+            // unplugged gamepads are released so the player can pick up another one
+            if gamepad.is_none_or(|gamepad| !gamepads.contains(gamepad)) {
+                *device = InputDevice::Gamepad(free.next());
+            }
+        }
+    }
+}
+
 pub fn fps_controller_input(
     key_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
     mut mouse_events: EventReader<MouseMotion>,
+    time: Res<Time>,
+    gamepads: Query<&Gamepad>,
     mut query: Query<(
         &GoldenControllerKeys,
+        &GoldenControllerButtons,
         &GoldenControllerMutables,
         &mut GoldenControllerInput,
+        Option<&InputDevice>,
     )>,
 ) {
-    for (controller, controller_mutables, mut input) in query.iter_mut() {
-        let mut mouse_delta = Vec2::ZERO;
-        for mouse_event in mouse_events.read() {
-            mouse_delta += mouse_event.delta;
+    let mut mouse_delta = Vec2::ZERO;
+    for mouse_event in mouse_events.read() {
+        mouse_delta += mouse_event.delta;
+    }
+
+    for (controller, buttons, controller_mutables, mut input, device) in query.iter_mut() {
+        let look_delta = match device.copied().unwrap_or_default() {
+            InputDevice::KeyboardMouse => {
+                input.movement = Vec3::new(
+                    get_axis(&key_input, controller.key_right, controller.key_left),
+                    0.0,
+                    get_axis(&key_input, controller.key_forward, controller.key_back),
+                );
+                input.lean = get_axis(
+                    &key_input,
+                    controller.key_lean_right,
+                    controller.key_lean_left,
+                );
+
+                input.jump = key_input.pressed(controller.key_jump);
+                input.crouch = key_input.pressed(controller.key_crouch);
+                input.fire = mouse_input.pressed(controller.button_fire);
+                input.interact = key_input.just_pressed(controller.key_use);
+                input.rotate = key_input.pressed(controller.key_rotate);
+                input.reload = key_input.just_pressed(controller.key_reload)
+                    || (input.reload && key_input.pressed(controller.key_reload));
+                input.melee = key_input.pressed(controller.key_melee);
+                input.throw = key_input.pressed(controller.key_throw);
+                input.aim = mouse_input.pressed(controller.button_aim);
+                let slot = (0..10).find(|slot| {
+                    key_input.just_pressed(controller.keys_weapon_slot[*slot as usize])
+                });
+                if let Some(slot) = slot {
+                    input.weapon_select = Some(WeaponSelect::Slot(slot));
+                } else if key_input.just_pressed(controller.key_last_weapon) {
+                    input.weapon_select = Some(WeaponSelect::Last);
+                }
+                if key_input.just_pressed(controller.key_fire_mode) {
+                    input.switch_fire_mode = true;
+                }
+                mouse_delta * controller_mutables.sensitivity
+            }
+            InputDevice::Gamepad(gamepad) => {
+                let Some(gamepad) = gamepad.and_then(|gamepad| gamepads.get(gamepad).ok()) else {
+                    // no gamepad yet, stand still instead of keeping the last input
+                    input.movement = Vec3::ZERO;
+                    input.lean = 0.0;
+                    input.jump = false;
+                    input.crouch = false;
+                    input.fire = false;
+                    input.interact = false;
+                    input.rotate = false;
+                    input.reload = false;
+                    input.melee = false;
+                    input.throw = false;
+                    input.aim = false;
+                    continue;
+                };
+                let stick = gamepad.left_stick();
+                input.movement = Vec3::new(stick.x, 0.0, stick.y);
+                input.lean = get_button_axis(gamepad, buttons.lean_right, buttons.lean_left);
+
+                input.jump = gamepad.pressed(buttons.jump);
+                input.crouch = gamepad.pressed(buttons.crouch);
+                input.fire = gamepad.pressed(buttons.fire);
+                input.interact = gamepad.just_pressed(buttons.interact);
+                input.rotate = gamepad.pressed(buttons.rotate);
+                input.reload = gamepad.just_pressed(buttons.reload)
+                    || (input.reload && gamepad.pressed(buttons.reload));
+                input.melee = gamepad.pressed(buttons.melee);
+                input.throw = gamepad.pressed(buttons.throw);
+                input.aim = gamepad.pressed(buttons.aim);
+                if gamepad.just_pressed(buttons.next_weapon) {
+                    input.weapon_select = Some(WeaponSelect::Next);
+                } else if gamepad.just_pressed(buttons.previous_weapon) {
+                    input.weapon_select = Some(WeaponSelect::Previous);
+                } else if gamepad.just_pressed(buttons.last_weapon) {
+                    input.weapon_select = Some(WeaponSelect::Last);
+                }
+                if gamepad.just_pressed(buttons.fire_mode) {
+                    input.switch_fire_mode = true;
+                }
+                // stick up looks up, mouse up is a negative delta
+                let look = gamepad.right_stick() * Vec2::new(1.0, -1.0);
+                look * buttons.look_speed * time.delta_secs()
+            }
+        };
+
+        if input.look_locked {
+            input.rotate_delta += look_delta;
+            continue;
         }
-        mouse_delta *= controller_mutables.sensitivity;
 
-        input.pitch = (input.pitch - mouse_delta.y)
+        input.pitch = (input.pitch - look_delta.y)
             .clamp(-FRAC_PI_2 + ANGLE_EPSILON, FRAC_PI_2 - ANGLE_EPSILON);
-        input.yaw -= mouse_delta.x;
+        input.yaw -= look_delta.x;
         if input.yaw.abs() > PI {
             input.yaw = input.yaw.rem_euclid(TAU);
         }
-
-        input.movement = Vec3::new(
-            get_axis(&key_input, controller.key_right, controller.key_left),
-            0.0,
-            get_axis(&key_input, controller.key_forward, controller.key_back),
-        );
-        input.lean = get_axis(
-            &key_input,
-            controller.key_lean_right,
-            controller.key_lean_left,
-        );
-
-        input.jump = key_input.pressed(controller.key_jump);
-        input.crouch = key_input.pressed(controller.key_crouch);
     }
 }
 
 fn scroll_events(
     mut evr_scroll: EventReader<MouseWheel>,
-    mut query: Query<&mut GoldenControllerInput>,
+    mut query: Query<(&mut GoldenControllerInput, Option<&InputDevice>)>,
 ) {
     let mut mod_shift = 0.0;
 
@@ -699,13 +1127,22 @@ fn scroll_events(
     }
     mod_shift = mod_shift.clamp(-1.0, 1.0);
 
-    for mut input in query.iter_mut() {
+    for (mut input, device) in query.iter_mut() {
+        // the scroll wheel belongs to whoever is on keyboard and mouse
+        if device.is_some_and(|device| *device != InputDevice::KeyboardMouse) {
+            continue;
+        }
+        // the wheel tunes lean or crouch depth while either is held, otherwise it cycles weapons
         if input.lean.abs() > 0.1 {
             input.lean_degree_mod += mod_shift;
             input.lean_degree_mod = input.lean_degree_mod.clamp(0.0, 1.0);
         } else if input.crouch {
             input.crouch_degree_mod -= mod_shift;
             input.crouch_degree_mod = input.crouch_degree_mod.clamp(0.0, 1.0);
+        } else if mod_shift > 0.0 {
+            input.weapon_select = Some(WeaponSelect::Previous);
+        } else if mod_shift < 0.0 {
+            input.weapon_select = Some(WeaponSelect::Next);
         }
     }
 }
@@ -719,6 +1156,11 @@ pub fn fps_controller_look(
     }
 }
 
+/// Rotation of the player's local frame, up is the local Y and forward is -Z
+fn local_basis(controller_mutables: &GoldenControllerMutables, yaw: f32) -> Quat {
+    controller_mutables.up_rotation * Quat::from_axis_angle(Vec3::Y, yaw)
+}
+
 /// Returns the offset that puts a point at the center of the player transform to the bottom of the collider.
 /// Needed for when we want to originate something at the foot of the player.
 fn collider_y_offset(collider: &Collider) -> Vec3 {
@@ -764,6 +1206,11 @@ fn get_axis(key_input: &Res<ButtonInput<KeyCode>>, key_pos: KeyCode, key_neg: Ke
     get_pressed(key_input, key_pos) - get_pressed(key_input, key_neg)
 }
 
+fn get_button_axis(gamepad: &Gamepad, button_pos: GamepadButton, button_neg: GamepadButton) -> f32 {
+    let pressed = |button| if gamepad.pressed(button) { 1.0 } else { 0.0 };
+    pressed(button_pos) - pressed(button_neg)
+}
+
 // ██████╗ ███████╗███╗   ██╗██████╗ ███████╗██████╗
 // ██╔══██╗██╔════╝████╗  ██║██╔══██╗██╔════╝██╔══██╗
 // ██████╔╝█████╗  ██╔██╗ ██║██║  ██║█████╗  ██████╔╝
@@ -789,8 +1236,8 @@ pub fn fps_controller_render(
         {
             let collider_offset = collider_y_offset(collider);
             let camera_offset = Vec3::Y * camera_config.height_offset;
-            render_transform.translation =
-                logical_transform.translation + collider_offset + camera_offset;
+            render_transform.translation = logical_transform.translation
+                + controller_mutables.up_rotation * (collider_offset + camera_offset);
             let pitch_quat = Quat::from_euler(EulerRot::YXZ, 0.0, controller_mutables.pitch, 0.0);
             render_transform.rotation = logical_transform.rotation.mul_quat(pitch_quat);
         }
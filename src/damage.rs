@@ -0,0 +1,278 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{DT, PlayerStats, ShotHit, projectile_impacts, shoot_bullet, trace_ballistic_shots};
+
+/// Health, armor and the [`DamageEvent`] pipeline, for players and anything else that can be hurt
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<DamageTaken>()
+            .add_event::<Died>()
+            .add_systems(
+                FixedUpdate,
+                (shot_damage, apply_damage, update_player_stats)
+                    .chain()
+                    // every way a shot can land is in before it's counted
+                    .after(shoot_bullet)
+                    .after(trace_ballistic_shots)
+                    .after(projectile_impacts),
+            );
+    }
+}
+
+/// The kinds of damage the pack deals, matching its `DamageType`s
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum DamageType {
+    #[default]
+    Normal,
+    Fire,
+    Melee,
+    Explosion,
+    Crossbow,
+}
+
+impl DamageType {
+    /// A DECORATE `DamageType` name, anything unknown is normal damage
+    pub fn from_decorate(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "fire" => Self::Fire,
+            "melee" | "crowdamage" => Self::Melee,
+            "explosion" => Self::Explosion,
+            "crossbow" => Self::Crossbow,
+            _ => Self::Normal,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            current: 100.0,
+            max: 100.0,
+        }
+    }
+}
+
+/// Soaks up part of the damage before it reaches [`Health`], wearing down as it does
+#[derive(Component, Clone)]
+pub struct Armor {
+    pub current: f32,
+    pub max: f32,
+    /// Share of each hit the armor takes, doom's green armor is 1/3 and blue 1/2
+    pub absorption: f32,
+}
+
+impl Default for Armor {
+    fn default() -> Self {
+        Self {
+            current: 100.0,
+            max: 200.0,
+            absorption: 1.0 / 3.0,
+        }
+    }
+}
+
+/// Put this on anything that should take [`DamageEvent`]s
+#[derive(Component, Default)]
+#[require(Health)]
+pub struct Damageable {
+    /// Damage multipliers like DECORATE's `DamageFactor`, types not listed take full damage
+    pub factors: Vec<(DamageType, f32)>,
+    /// Seconds nothing hurts after taking damage. The pellets of a shotgun blast that hit the
+    /// same thing add up to one event, so they all count.
+    pub invulnerability: f32,
+    /// Seconds of invulnerability left
+    pub invulnerable_for: f32,
+}
+
+impl Damageable {
+    pub fn factor(&self, damage_type: DamageType) -> f32 {
+        self.factors
+            .iter()
+            .find(|(factor_type, _)| *factor_type == damage_type)
+            .map_or(1.0, |(_, factor)| *factor)
+    }
+}
+
+/// Hurts `target` on the next fixed tick
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub damage_type: DamageType,
+    /// Whoever dealt it, the logical player for shots
+    pub source: Option<Entity>,
+    pub hit_point: Option<Vec3>,
+}
+
+/// Damage that got through, after factors and armor
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageTaken {
+    pub target: Entity,
+    /// Taken off health
+    pub amount: f32,
+    /// Taken off armor
+    pub absorbed: f32,
+    pub damage_type: DamageType,
+    pub source: Option<Entity>,
+    pub hit_point: Option<Vec3>,
+}
+
+/// Health ran out
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Died {
+    pub entity: Entity,
+    pub damage_type: DamageType,
+    pub source: Option<Entity>,
+}
+
+/// System: shots that hit damageable things hurt them, every pellet of a shot that hit the
+/// same thing adding up to one event
+fn shot_damage(
+    mut shot_hits: EventReader<ShotHit>,
+    mut damage: EventWriter<DamageEvent>,
+    damageables: Query<(), With<Damageable>>,
+    collider_bodies: Query<&ColliderOf>,
+) {
+    // in the order things were first hit, pointing at where the first pellet landed
+    let mut combined: Vec<((Entity, Entity, u32), DamageEvent)> = Vec::new();
+    for hit in shot_hits.read() {
+        // a child collider hurts the body it belongs to
+        let target = collider_bodies
+            .get(hit.entity)
+            .map_or(hit.entity, |collider_of| collider_of.body);
+        if !damageables.contains(target) {
+            continue;
+        }
+        let key = (hit.shooter, target, hit.shot);
+        match combined.iter_mut().find(|(other, _)| *other == key) {
+            Some((_, event)) => event.amount += hit.damage,
+            None => combined.push((
+                key,
+                DamageEvent {
+                    target,
+                    amount: hit.damage,
+                    damage_type: hit.damage_type,
+                    source: Some(hit.shooter),
+                    hit_point: Some(hit.point),
+                },
+            )),
+        }
+    }
+    damage.write_batch(combined.into_iter().map(|(_, event)| event));
+}
+
+/// System: run damage through factors, invulnerability and armor, then take it off health
+fn apply_damage(
+    mut events: EventReader<DamageEvent>,
+    mut taken: EventWriter<DamageTaken>,
+    mut died: EventWriter<Died>,
+    mut query: Query<(&mut Damageable, &mut Health, Option<&mut Armor>)>,
+) {
+    for (mut damageable, _, _) in &mut query {
+        damageable.invulnerable_for = (damageable.invulnerable_for - DT).max(0.0);
+    }
+
+    for event in events.read() {
+        let Ok((mut damageable, mut health, armor)) = query.get_mut(event.target) else {
+            continue;
+        };
+        // the dead stay dead until something resets their health
+        if health.current <= 0.0 || damageable.invulnerable_for > 0.0 {
+            continue;
+        }
+        let mut amount = event.amount * damageable.factor(event.damage_type);
+        if amount <= 0.0 {
+            continue;
+        }
+
+        // like doom, the armor takes its share for as long as it lasts
+        let mut absorbed = 0.0;
+        if let Some(mut armor) = armor {
+            absorbed = (amount * armor.absorption).min(armor.current);
+            armor.current -= absorbed;
+            amount -= absorbed;
+        }
+        amount = amount.min(health.current);
+        health.current -= amount;
+        damageable.invulnerable_for = damageable.invulnerability;
+
+        taken.write(DamageTaken {
+            target: event.target,
+            amount,
+            absorbed,
+            damage_type: event.damage_type,
+            source: event.source,
+            hit_point: event.hit_point,
+        });
+        if health.current <= 0.0 {
+            died.write(Died {
+                entity: event.target,
+                damage_type: event.damage_type,
+                source: event.source,
+            });
+        }
+    }
+}
+
+/// System: copy health and armor into the [`PlayerStats`] of players that have them
+fn update_player_stats(mut query: Query<(&Health, Option<&Armor>, &mut PlayerStats)>) {
+    for (health, armor, mut stats) in &mut query {
+        stats.health = health.current.ceil() as i16;
+        stats.max_health = health.max as i16;
+        if let Some(armor) = armor {
+            stats.armor = armor.current.ceil() as i16;
+            stats.max_armor = armor.max as i16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shots_hurt_the_body_behind_a_child_collider() {
+        let mut app = App::new();
+        app.add_event::<ShotHit>()
+            .add_event::<DamageEvent>()
+            .add_systems(Update, shot_damage);
+        let body = app.world_mut().spawn(Damageable::default()).id();
+        let head = app.world_mut().spawn(ColliderOf { body }).id();
+        let shooter = app.world_mut().spawn_empty().id();
+        let hit = ShotHit {
+            shooter,
+            entity: head,
+            point: Vec3::ZERO,
+            normal: Vec3::Z,
+            direction: Dir3::NEG_Z,
+            distance: 1.0,
+            damage: 10.0,
+            damage_type: DamageType::Fire,
+            shot: 1,
+        };
+        app.world_mut()
+            .send_event_batch([hit, ShotHit { damage: 5.0, ..hit }]);
+        app.update();
+
+        let events: Vec<_> = app
+            .world_mut()
+            .resource_mut::<Events<DamageEvent>>()
+            .drain()
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].target, body);
+        assert_eq!(events[0].amount, 15.0);
+        assert_eq!(events[0].damage_type, DamageType::Fire);
+    }
+}
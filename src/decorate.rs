@@ -0,0 +1,2322 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+
+use crate::{
+    DamageType, ExplosiveDef, FireMode, LightDef, MELEE_RANGE, MUZZLE_FLASH_LIGHT, Recoil,
+    ReloadStyle, ShotKind, Sights, SpreadPattern, TriggerMode, WeaponDef, WeaponSlots,
+    WeaponSounds, parse_gldefs, punch_damage,
+};
+
+/// Imports the weapons of a ZDoom DECORATE pack at startup, see [`DecorateWeapons`]
+pub struct DecoratePlugin {
+    /// Pack folder inside the assets folder
+    pub root: String,
+    /// DECORATE lump inside the pack
+    pub entry: String,
+    /// Shot kinds replacing the imported hitscan, by actor name
+    pub shots: Vec<(String, ShotKind)>,
+    /// Spread patterns replacing ZDoom's random spread, by actor name
+    pub patterns: Vec<(String, SpreadPattern)>,
+    /// Sights replacing the imported ones, by actor name
+    pub sights: Vec<(String, Sights)>,
+}
+
+impl Default for DecoratePlugin {
+    fn default() -> Self {
+        Self {
+            root: "AshesWeaponsV357".to_string(),
+            entry: "DECORATE.TXT".to_string(),
+            // the pack's long guns fire rounds that drop and go through things
+            shots: [
+                ("FAL", rifle_round()),
+                ("FAL2", rifle_round()),
+                ("FAL3", rifle_round()),
+                ("Leveraction", lever_round()),
+                ("Leveraction2", lever_round()),
+                ("JunkerMusket", musket_ball()),
+                ("JunkerMusket2", musket_ball()),
+                ("JunkerMusket3", musket_ball()),
+            ]
+            .map(|(name, shot)| (name.to_string(), shot))
+            .into(),
+            // the pump-action and sawed-off throw the same even pattern every shot
+            patterns: [
+                "pumpaction",
+                "pumpaction2",
+                "pumpaction3",
+                "SawedOff",
+                "SawedOff2",
+                "SawedOff3",
+                "HunterShotgun",
+                "HunterShotgun2",
+                "HunterShotgun3",
+            ]
+            .map(|name| (name.to_string(), SpreadPattern::Even))
+            .into(),
+            // the bolt-casters have no zoom of their own, they get a scope
+            sights: ["Xbow", "Xbow2", "Xbow3"]
+                .map(|name| (name.to_string(), crossbow_scope()))
+                .into(),
+        }
+    }
+}
+
+impl Plugin for DecoratePlugin {
+    fn build(&self, app: &mut App) {
+        let assets_dir = FileAssetReader::get_base_path().join("assets");
+        let mut import = import_decorate(&assets_dir, &self.root, &self.entry);
+        for def in &mut import.weapons {
+            let shot = self
+                .shots
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&def.name));
+            if let Some((_, shot)) = shot {
+                for mode in &mut def.fire_modes {
+                    mode.shot = *shot;
+                }
+            }
+            let pattern = self
+                .patterns
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&def.name));
+            if let Some((_, pattern)) = pattern {
+                for mode in &mut def.fire_modes {
+                    mode.pattern = pattern.clone();
+                }
+            }
+            let sights = self
+                .sights
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&def.name));
+            if let Some((_, sights)) = sights {
+                def.sights = sights.clone();
+            }
+        }
+        for diagnostic in &import.diagnostics {
+            warn!("{diagnostic}");
+        }
+        info!(
+            "imported {} weapons from {}/{}",
+            import.weapons.len(),
+            self.root,
+            self.entry
+        );
+        app.insert_resource(PendingDecorateImport(import))
+            .add_systems(PreStartup, add_decorate_weapons);
+    }
+}
+
+fn rifle_round() -> ShotKind {
+    ShotKind::Ballistic {
+        speed: 840.0,
+        drag: 0.0008,
+        max_range: HITSCAN_RANGE / MAP_UNITS_PER_METER,
+        penetration: 0.3,
+        tracer: true,
+    }
+}
+
+fn lever_round() -> ShotKind {
+    ShotKind::Ballistic {
+        speed: 600.0,
+        drag: 0.0012,
+        max_range: HITSCAN_RANGE / MAP_UNITS_PER_METER,
+        penetration: 0.2,
+        tracer: false,
+    }
+}
+
+/// Slow and soft, it drops a lot and barely goes through anything
+fn musket_ball() -> ShotKind {
+    ShotKind::Ballistic {
+        speed: 450.0,
+        drag: 0.002,
+        max_range: HITSCAN_RANGE / MAP_UNITS_PER_METER,
+        penetration: 0.1,
+        tracer: false,
+    }
+}
+
+fn crossbow_scope() -> Sights {
+    Sights {
+        zoom: 4.0,
+        time: 0.3,
+        spread: 0.0,
+        offset: (0.0, 0.0),
+        scope: true,
+    }
+}
+
+/// Imported weapons by actor name, ready before `Startup`
+#[derive(Resource, Default)]
+pub struct DecorateWeapons {
+    /// Lowercase actor name
+    weapons: HashMap<String, Handle<WeaponDef>>,
+    pub players: Vec<PlayerClass>,
+    /// Weapon tiers from the pack's modkit script, by actor name
+    pub upgrades: Vec<ModkitUpgrade>,
+    /// GLDEFS lights by lowercase name
+    pub lights: HashMap<String, LightDef>,
+    /// Sounds of the puffs `A_CustomPunch` names, by lowercase actor name
+    pub puffs: HashMap<String, PuffSounds>,
+    pub diagnostics: Vec<DecorateDiagnostic>,
+}
+
+impl DecorateWeapons {
+    pub fn get(&self, name: &str) -> Option<Handle<WeaponDef>> {
+        self.weapons.get(&name.to_lowercase()).cloned()
+    }
+
+    pub fn player(&self, class: &str) -> Option<&PlayerClass> {
+        self.players
+            .iter()
+            .find(|player| player.name.eq_ignore_ascii_case(class))
+    }
+
+    /// A player class's slots, leaving out the weapons that weren't imported
+    pub fn slots(&self, class: &str) -> Option<WeaponSlots> {
+        let player = self.player(class)?;
+        Some(WeaponSlots {
+            slots: player
+                .slots
+                .iter()
+                .map(|slot| slot.iter().filter_map(|name| self.get(name)).collect())
+                .collect(),
+            ..default()
+        })
+    }
+}
+
+#[derive(Resource)]
+struct PendingDecorateImport(DecorateImport);
+
+fn add_decorate_weapons(
+    mut commands: Commands,
+    mut pending: ResMut<PendingDecorateImport>,
+    mut weapon_defs: ResMut<Assets<WeaponDef>>,
+) {
+    let import = std::mem::take(&mut pending.0);
+    commands.remove_resource::<PendingDecorateImport>();
+    commands.insert_resource(DecorateWeapons {
+        weapons: import
+            .weapons
+            .into_iter()
+            .map(|def| (def.name.to_lowercase(), weapon_defs.add(def)))
+            .collect(),
+        players: import.players,
+        upgrades: import.upgrades,
+        lights: import.lights,
+        puffs: import.puffs,
+        diagnostics: import.diagnostics,
+    });
+}
+
+/// Doom map units are roughly 32 to the metre, a 56 unit tall player is 1.75m
+pub const MAP_UNITS_PER_METER: f32 = 32.0;
+
+/// Doom game logic ticks at 35 Hz, state durations are counted in these
+pub const TICS_PER_SECOND: f32 = 35.0;
+
+/// Range of a ZDoom hitscan attack
+const HITSCAN_RANGE: f32 = 8192.0;
+
+/// Spawn frames followed looking for a thrown actor's fuse, pipe bombs count down for ~100
+const MAX_FUSE_FRAMES: usize = 4096;
+
+/// `A_ZoomFactor` from which a weapon is taken to look through a scope
+const SCOPE_ZOOM: f32 = 2.0;
+
+/// The pack's guns don't aim, so they all share this: a slight zoom,
+/// with the gun brought in toward the middle of the screen
+const IRON_SIGHTS: Sights = Sights {
+    zoom: 1.3,
+    time: 0.2,
+    spread: 0.4,
+    offset: (-48.0, 8.0),
+    scope: false,
+};
+
+/// Action functions the weapon state machine knows about, anything else is reported
+const SUPPORTED_ACTIONS: &[&str] = &[
+    "a_weaponready",
+    "a_raise",
+    "a_lower",
+    "a_refire",
+    "a_clearrefire",
+    "a_firebullets",
+    "a_fireprojectile",
+    "a_firecustommissile",
+    "a_custompunch",
+    "a_throwgrenade",
+    "a_playsound",
+    "a_playweaponsound",
+    "a_startsound",
+    "a_stopsound",
+    "a_jump",
+    "a_jumpifinventory",
+    "a_giveinventory",
+    "a_takeinventory",
+    "a_checkreload",
+    "a_setpitch",
+    "a_light",
+    "a_light0",
+    "a_light1",
+    "a_light2",
+    "a_gunflash",
+    "a_alertmonsters",
+    // read into the sights, aiming does the zooming
+    "a_zoomfactor",
+];
+
+/// Stands in for the action name of `{ ... }` blocks, which are never supported
+const ANONYMOUS_FUNCTION: &str = "anonymous function";
+
+/// Labels every weapon is expected to have, also where one state sequence ends
+const WEAPON_LABELS: &[&str] = &[
+    "ready", "deselect", "select", "fire", "altfire", "hold", "althold", "reload", "zoom", "user1",
+    "user2", "user3", "user4", "flash", "altflash", "spawn",
+];
+
+/// Something the importer could not handle, with where it is
+#[derive(Clone, Debug)]
+pub struct DecorateDiagnostic {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DecorateDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+/// A weapon's DECORATE states, stepped by the first person weapon state machine
+#[derive(Clone, Debug, Default)]
+pub struct WeaponStates {
+    pub frames: Vec<StateFrame>,
+    /// Lowercase label to index into `frames`
+    pub labels: HashMap<String, usize>,
+    /// Sprite lump, like `FFAFA0`, to how it is drawn
+    pub sprites: HashMap<String, SpriteLayout>,
+    /// Lowercase logical sound name to asset paths, more than one for `$random` sounds
+    pub sounds: HashMap<String, Vec<String>>,
+    /// Lowercase item `A_FireBullets` takes its rounds from, `Weapon.AmmoType1`
+    pub primary_ammo: Option<String>,
+    /// `Inventory.MaxAmount` of the lowercase items the actions give, take and check
+    pub item_limits: HashMap<String, u32>,
+    /// Lowercase actors `A_ThrowGrenade` throws
+    pub thrown: HashMap<String, ExplosiveDef>,
+    /// Lowercase actors `A_FireProjectile` and `A_FireCustomMissile` fire
+    pub missiles: HashMap<String, MissileDef>,
+}
+
+impl WeaponStates {
+    pub fn label(&self, label: &str) -> Option<usize> {
+        self.labels.get(&label.to_lowercase()).copied()
+    }
+}
+
+/// A projectile actor, from its `Speed` and `Damage`
+#[derive(Clone, Copy, Debug)]
+pub struct MissileDef {
+    /// m/s
+    pub speed: f32,
+    /// Average damage of a hit
+    pub damage: f32,
+    pub damage_type: DamageType,
+}
+
+#[derive(Clone, Debug)]
+pub struct StateFrame {
+    pub sprite: String,
+    pub frame: char,
+    /// -1 lasts forever
+    pub tics: i32,
+    pub bright: bool,
+    pub action: Option<StateAction>,
+    pub next: StateNext,
+    pub line: usize,
+}
+
+impl StateFrame {
+    /// Lump name of the sprite shown, `None` for the invisible `TNT1`
+    pub fn lump(&self) -> Option<String> {
+        (!self.sprite.eq_ignore_ascii_case("tnt1"))
+            .then(|| format!("{}{}0", self.sprite.to_uppercase(), self.frame))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StateAction {
+    /// Lowercase, like `a_firebullets`
+    pub name: String,
+    /// Raw argument source, strings keep their quotes
+    pub args: Vec<String>,
+}
+
+impl StateAction {
+    pub fn arg(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str)
+    }
+
+    pub fn arg_f32(&self, index: usize) -> Option<f32> {
+        self.arg(index).and_then(|arg| arg.parse().ok())
+    }
+
+    /// A string argument without its quotes
+    pub fn arg_str(&self, index: usize) -> Option<&str> {
+        self.arg(index)
+            .and_then(|arg| arg.strip_prefix('"'))
+            .and_then(|arg| arg.strip_suffix('"'))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StateNext {
+    /// Fall through to the following frame
+    Next,
+    Jump(usize),
+    Stop,
+    /// Stay on this frame forever
+    Wait,
+}
+
+/// A sprite composed from one or more patches on a canvas, from TEXTURES.
+/// Sprites that are plain image files have no size and one patch at the origin.
+#[derive(Clone, Debug)]
+pub struct SpriteLayout {
+    pub size: Option<UVec2>,
+    /// Where the canvas is anchored, doom style, for weapons relative to the screen centre
+    pub offset: IVec2,
+    pub scale: Vec2,
+    pub patches: Vec<SpritePatch>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpritePatch {
+    /// Asset path of the image
+    pub image: String,
+    pub position: IVec2,
+}
+
+/// A player class's `Player.WeaponSlot` and `Player.StartItem` lines
+#[derive(Clone, Debug, Default)]
+pub struct PlayerClass {
+    pub name: String,
+    /// Weapon actor names by slot number, 0 to 9
+    pub slots: [Vec<String>; 10],
+    /// Item and amount, in the order given
+    pub start_items: Vec<(String, u32)>,
+}
+
+/// Everything `import_decorate` found
+#[derive(Default)]
+pub struct DecorateImport {
+    pub weapons: Vec<WeaponDef>,
+    /// Actors with weapon slots
+    pub players: Vec<PlayerClass>,
+    pub upgrades: Vec<ModkitUpgrade>,
+    pub lights: HashMap<String, LightDef>,
+    pub puffs: HashMap<String, PuffSounds>,
+    pub diagnostics: Vec<DecorateDiagnostic>,
+}
+
+/// Asset paths a melee puff plays, one of each list is picked at random
+#[derive(Clone, Debug, Default)]
+pub struct PuffSounds {
+    pub miss: Vec<String>,
+    pub wall: Vec<String>,
+    pub flesh: Vec<String>,
+}
+
+/// One `checkweapon` block of the pack's MODKITS script, installing a kit swaps `from` for `to`
+#[derive(Clone, Debug)]
+pub struct ModkitUpgrade {
+    pub from: String,
+    pub to: String,
+    /// The item used up, `ItemModkit`
+    pub item: String,
+    /// Printed once it's installed, like "You installed the Cro-Hammer Head (PULVERISER MOD)!"
+    pub message: String,
+    pub sound: Option<String>,
+}
+
+/// Imports every weapon actor reachable from `entry`, following `#include`s.
+/// `root` is the pack folder relative to `assets_dir`, produced asset paths start with it.
+pub fn import_decorate(assets_dir: &Path, root: &str, entry: &str) -> DecorateImport {
+    let mut import = DecorateImport::default();
+    let files = FileIndex::new(&assets_dir.join(root), root);
+
+    let mut actors = HashMap::new();
+    let mut pending = vec![(entry.to_string(), 0, entry.to_string())];
+    while let Some((path, line, from)) = pending.pop() {
+        let Some(source) = files.read(&path) else {
+            import.diagnostics.push(DecorateDiagnostic {
+                file: from,
+                line,
+                message: format!("can't read include `{path}`"),
+            });
+            continue;
+        };
+        for item in parse_decorate(&source, &path, &mut import.diagnostics) {
+            match item {
+                DecorateItem::Include {
+                    path: include,
+                    line,
+                } => pending.push((include, line, path.clone())),
+                DecorateItem::Actor(actor) => {
+                    actors.insert(actor.name.to_lowercase(), *actor);
+                }
+            }
+        }
+    }
+
+    let sound_info = files
+        .read("SNDINFO")
+        .map(|source| SoundInfo::parse(&source))
+        .unwrap_or_default();
+    let sprites = files
+        .read("TEXTURES")
+        .map(|source| parse_textures(&source, &files))
+        .unwrap_or_default();
+
+    let mut names = actors.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    import.players = names
+        .iter()
+        .filter_map(|name| player_class(&actors[name]))
+        .collect();
+    for name in names {
+        let actor = &actors[&name];
+        if !inherits(&actors, &name, "weapon") || actor.frames.is_empty() {
+            continue;
+        }
+        for frame in &actor.frames {
+            let Some(action) = &frame.action else {
+                continue;
+            };
+            if !SUPPORTED_ACTIONS.contains(&action.name.as_str()) {
+                import.diagnostics.push(DecorateDiagnostic {
+                    file: actor.file.clone(),
+                    line: frame.line,
+                    message: format!("{}: unsupported action `{}`", actor.name, action.name),
+                });
+            }
+        }
+
+        let mut states = resolve_states(&actors, &name, 0, &mut import.diagnostics);
+        let mut used_sprites = HashMap::new();
+        let mut used_sounds = HashMap::new();
+        for frame in &states.frames {
+            let layout = frame.lump().and_then(|lump| {
+                let layout = sprites.get(&lump).cloned().or_else(|| {
+                    files.image(&lump).map(|image| SpriteLayout {
+                        size: None,
+                        offset: IVec2::ZERO,
+                        scale: Vec2::ONE,
+                        patches: vec![SpritePatch {
+                            image,
+                            position: IVec2::ZERO,
+                        }],
+                    })
+                })?;
+                Some((lump, layout))
+            });
+            if let Some((lump, layout)) = layout {
+                used_sprites.insert(lump, layout);
+            }
+            if let Some(sound) = frame.action.as_ref().and_then(sound_name) {
+                let paths = sound_info.resolve(sound, &files);
+                if !paths.is_empty() {
+                    used_sounds.insert(sound.to_lowercase(), paths);
+                }
+            }
+        }
+        states.sprites = used_sprites;
+        states.sounds = used_sounds;
+        states.item_limits = states
+            .frames
+            .iter()
+            .filter_map(|frame| frame.action.as_ref())
+            .filter(|action| {
+                matches!(
+                    action.name.as_str(),
+                    "a_jumpifinventory" | "a_giveinventory" | "a_takeinventory"
+                )
+            })
+            .filter_map(|action| {
+                let item = action.arg_str(0)?;
+                let limit = property(&actors, item, "inventory.maxamount")?
+                    .first()?
+                    .parse()
+                    .ok()?;
+                Some((item.to_lowercase(), limit))
+            })
+            .collect();
+        states.thrown = states
+            .frames
+            .iter()
+            .filter_map(|frame| frame.action.as_ref())
+            .filter(|action| action.name == "a_throwgrenade")
+            .filter_map(|action| {
+                let name = action.arg(0)?.trim_matches('"').to_lowercase();
+                let def = explosive_def(&actors, &name, &mut import.diagnostics)?;
+                Some((name, def))
+            })
+            .collect();
+        states.missiles = states
+            .frames
+            .iter()
+            .filter_map(|frame| frame.action.as_ref())
+            .filter(|action| {
+                matches!(
+                    action.name.as_str(),
+                    "a_fireprojectile" | "a_firecustommissile"
+                )
+            })
+            .filter_map(|action| {
+                let name = action.arg_str(0)?.to_lowercase();
+                let def = missile_def(&actors, &name)?;
+                // custom missiles are mostly smoke and casings, only the ones that hurt are shots
+                (action.name == "a_fireprojectile" || def.damage > 0.0).then_some((name, def))
+            })
+            .collect();
+
+        match weapon_def(&actors, actor, states) {
+            Ok(def) => import.weapons.push(def),
+            Err(message) => import.diagnostics.push(DecorateDiagnostic {
+                file: actor.file.clone(),
+                line: actor.line,
+                message: format!("{}: {message}", actor.name),
+            }),
+        }
+    }
+
+    let mut puffs = actors
+        .values()
+        .flat_map(|actor| &actor.frames)
+        .filter_map(|frame| frame.action.as_ref())
+        .filter(|action| action.name == "a_custompunch")
+        .filter_map(|action| action.arg_str(3))
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    puffs.sort();
+    puffs.dedup();
+    import.puffs = puffs
+        .into_iter()
+        .map(|name| {
+            let sounds = puff_sounds(&actors, &name, &sound_info, &files, &mut import.diagnostics);
+            (name, sounds)
+        })
+        .collect();
+
+    import.upgrades = files
+        .read("MODKITS")
+        .map(|source| parse_modkits(&source, &sound_info, &files))
+        .unwrap_or_default();
+    import.lights = files
+        .read("GLDEFS")
+        .map(|source| parse_gldefs(&source))
+        .unwrap_or_default();
+
+    // inherited states are resolved once per child, so the same problem can come up more than once
+    let mut seen = std::collections::HashSet::new();
+    import
+        .diagnostics
+        .retain(|diagnostic| seen.insert(diagnostic.to_string()));
+    import
+}
+
+/// How zdoom sounds a melee puff: `ActiveSound` on a miss, `AttackSound` or else
+/// the `Crash` or `Spawn` state's sounds on a wall, `SeeSound` or else the `Melee` state's on flesh
+fn puff_sounds(
+    actors: &HashMap<String, ParsedActor>,
+    name: &str,
+    sound_info: &SoundInfo,
+    files: &FileIndex,
+    diagnostics: &mut Vec<DecorateDiagnostic>,
+) -> PuffSounds {
+    let states = resolve_states(actors, name, 0, diagnostics);
+    let from_property = |name_of: &str| {
+        property(actors, name, name_of)
+            .and_then(|values| values.first())
+            .map(|sound| sound_info.resolve(sound, files))
+            .unwrap_or_default()
+    };
+    let from_state = |label: &str| {
+        let Some(start) = states.label(label) else {
+            return Vec::new();
+        };
+        reachable(&states, start)
+            .into_iter()
+            .filter_map(|index| states.frames[index].action.as_ref().and_then(sound_name))
+            .flat_map(|sound| sound_info.resolve(sound, files))
+            .collect::<Vec<_>>()
+    };
+
+    let mut wall = from_property("attacksound");
+    if wall.is_empty() {
+        wall = from_state("crash");
+    }
+    if wall.is_empty() {
+        wall = from_state("spawn");
+    }
+    let mut flesh = from_property("seesound");
+    if flesh.is_empty() {
+        flesh = from_state("melee");
+    }
+    PuffSounds {
+        miss: from_property("activesound"),
+        wall,
+        flesh,
+    }
+}
+
+/// The weapon swaps of the MODKITS ACS library. It's one long `if` chain of
+/// `checkweapon`, `giveinventory` and `Print` calls, so scanning for those is enough.
+fn parse_modkits(source: &str, sound_info: &SoundInfo, files: &FileIndex) -> Vec<ModkitUpgrade> {
+    // ACS is case insensitive, lowercasing ascii keeps the byte offsets
+    let lower = source.to_ascii_lowercase();
+    let mut upgrades = Vec::new();
+    let starts = lower.match_indices("checkweapon(").map(|(start, _)| start);
+    for start in starts {
+        let end = lower[start..]
+            .find("terminate")
+            .map_or(source.len(), |end| start + end);
+        let block = &source[start..end];
+        let lower = &lower[start..end];
+        // the first string argument of every call to `function` in the block
+        let strings = move |function: &'static str| {
+            lower.match_indices(function).filter_map(move |(at, _)| {
+                let open = at + block[at..].find('"')? + 1;
+                let close = open + block[open..].find('"')?;
+                Some(&block[open..close])
+            })
+        };
+        let Some(from) = strings("checkweapon(").next() else {
+            continue;
+        };
+        let (Some(to), Some(item), Some(message)) = (
+            strings("giveinventory(").next(),
+            strings("takeinventory(").find(|item| !item.eq_ignore_ascii_case(from)),
+            strings("print(").next(),
+        ) else {
+            continue;
+        };
+        upgrades.push(ModkitUpgrade {
+            from: from.to_string(),
+            to: to.to_string(),
+            item: item.to_string(),
+            message: message.to_string(),
+            sound: strings("playsound(")
+                .next()
+                .and_then(|sound| sound_info.resolve(sound, files).into_iter().next()),
+        });
+    }
+    upgrades
+}
+
+fn player_class(actor: &ParsedActor) -> Option<PlayerClass> {
+    let mut class = PlayerClass {
+        name: actor.name.clone(),
+        ..default()
+    };
+    let mut has_slots = false;
+    for (property, values) in &actor.properties {
+        match property.as_str() {
+            "player.weaponslot" => {
+                let Some(slot) = values
+                    .first()
+                    .and_then(|slot| slot.parse::<usize>().ok())
+                    .filter(|slot| *slot < class.slots.len())
+                else {
+                    continue;
+                };
+                class.slots[slot] = values[1..]
+                    .iter()
+                    .filter(|weapon| !weapon.is_empty())
+                    .cloned()
+                    .collect();
+                has_slots = true;
+            }
+            "player.startitem" => {
+                let Some(item) = values.first() else {
+                    continue;
+                };
+                let amount = values.get(1).and_then(|amount| amount.parse().ok());
+                class.start_items.push((item.clone(), amount.unwrap_or(1)));
+            }
+            _ => {}
+        }
+    }
+    has_slots.then_some(class)
+}
+
+/// The sound an action plays, if it plays one
+pub fn sound_name(action: &StateAction) -> Option<&str> {
+    matches!(
+        action.name.as_str(),
+        "a_playsound" | "a_playweaponsound" | "a_startsound"
+    )
+    .then(|| action.arg_str(0))
+    .flatten()
+}
+
+fn weapon_def(
+    actors: &HashMap<String, ParsedActor>,
+    actor: &ParsedActor,
+    mut states: WeaponStates,
+) -> Result<WeaponDef, String> {
+    let fire_index = states
+        .label("fire")
+        .map(|fire| reachable(&states, fire))
+        .unwrap_or_default()
+        .into_iter()
+        .find(|index| {
+            states.frames[*index].action.as_ref().is_some_and(|action| {
+                match action.name.as_str() {
+                    "a_firebullets" | "a_fireprojectile" | "a_custompunch" => true,
+                    // skip the smoke and casings fired alongside the shot
+                    "a_firecustommissile" => action
+                        .arg_str(0)
+                        .is_some_and(|name| states.missiles.contains_key(&name.to_lowercase())),
+                    _ => false,
+                }
+            })
+        })
+        .ok_or("no A_FireBullets, A_FireProjectile, A_FireCustomMissile or A_CustomPunch")?;
+    let fire = states.frames[fire_index].action.as_ref().unwrap();
+    let melee = fire.name == "a_custompunch";
+    let number = |index: usize, what: &str| {
+        fire.arg_f32(index).ok_or_else(|| {
+            format!(
+                "A_FireBullets {what} `{}` is not a plain number",
+                fire.arg(index).unwrap_or_default()
+            )
+        })
+    };
+    let (spread, count, damage, damage_type, shot) = if melee {
+        // the swing rolls its own damage when the states get there, this is the average blow
+        let damage = fire
+            .arg(0)
+            .and_then(|arg| punch_damage(arg, average))
+            .unwrap_or_default();
+        let max_range = fire.arg_f32(4).unwrap_or(MELEE_RANGE) / MAP_UNITS_PER_METER;
+        let damage_type = fire
+            .arg_str(3)
+            .and_then(|puff| damage_type(actors, puff))
+            .unwrap_or(DamageType::Melee);
+        let shot = ShotKind::Hitscan { max_range };
+        ((0.0, 0.0), 1, damage, damage_type, shot)
+    } else if fire.name != "a_firebullets" {
+        let missile = fire.arg_str(0).unwrap_or_default();
+        let def = states
+            .missiles
+            .get(&missile.to_lowercase())
+            .ok_or_else(|| format!("projectile actor `{missile}` has no Speed"))?;
+        let shot = ShotKind::Projectile { speed: def.speed };
+        ((0.0, 0.0), 1, def.damage, def.damage_type, shot)
+    } else {
+        // zdoom mirrors a negative spread, which comes to the same thing
+        let spread = (
+            number(0, "horizontal spread")?.abs(),
+            number(1, "vertical spread")?.abs(),
+        );
+        let shot = ShotKind::Hitscan {
+            max_range: HITSCAN_RANGE / MAP_UNITS_PER_METER,
+        };
+        // bullets hurt the way their puff does
+        let damage_type = fire
+            .arg_str(4)
+            .and_then(|puff| damage_type(actors, puff))
+            .unwrap_or_default();
+        (
+            spread,
+            number(2, "bullet count")? as i32,
+            number(3, "damage")?,
+            damage_type,
+            shot,
+        )
+    };
+
+    // the shot's own frames, from the frames leading straight into it until the weapon is ready again
+    let ready = states.label("ready");
+    let mut sequence = Vec::new();
+    let mut index = fire_index;
+    while index > 0 && states.frames[index - 1].next == StateNext::Next {
+        index -= 1;
+    }
+    while sequence.len() < 256 && Some(index) != ready && !sequence.contains(&index) {
+        let Some(frame) = states.frames.get(index) else {
+            break;
+        };
+        sequence.push(index);
+        index = match frame.next {
+            StateNext::Next => index + 1,
+            StateNext::Jump(target) => target,
+            StateNext::Stop | StateNext::Wait => break,
+        };
+    }
+    let sequence = sequence
+        .into_iter()
+        .map(|index| &states.frames[index])
+        .collect::<Vec<_>>();
+    let cooldown =
+        sequence.iter().map(|frame| frame.tics.max(0)).sum::<i32>() as f32 / TICS_PER_SECOND;
+
+    let sound_path = |frame: &StateFrame| {
+        let sound = frame.action.as_ref().and_then(sound_name)?;
+        states.sounds.get(&sound.to_lowercase())?.first().cloned()
+    };
+    let fire_sound = sequence.iter().find_map(|frame| sound_path(frame));
+    let dry_fire = states.frames.iter().find_map(|frame| {
+        let sound = frame.action.as_ref().and_then(sound_name)?;
+        sound
+            .to_lowercase()
+            .contains("dryfire")
+            .then(|| sound_path(frame))
+            .flatten()
+    });
+    let reload = states.label("reload").and_then(|reload| {
+        // reloads hop between helper labels, so scan in file order up to the next weapon label
+        states.frames[reload..]
+            .iter()
+            .enumerate()
+            .take_while(|(offset, _)| {
+                *offset == 0
+                    || !states.labels.iter().any(|(label, index)| {
+                        *index == reload + offset && WEAPON_LABELS.contains(&label.as_str())
+                    })
+            })
+            .find_map(|(_, frame)| sound_path(frame))
+    });
+
+    let ammo_type1 = property(actors, &actor.name, "weapon.ammotype1")
+        .or_else(|| property(actors, &actor.name, "weapon.ammotype"));
+    let ammo_type2 = property(actors, &actor.name, "weapon.ammotype2");
+    // the pack keeps the loaded rounds in their own ammo type, like "Falloaded"
+    let magazine = ammo_type1.and_then(|loaded| {
+        ammo_type2?;
+        let name = loaded.first()?;
+        property(actors, name, "inventory.maxamount")?
+            .first()?
+            .parse()
+            .ok()
+    });
+    let ammo_type = match magazine {
+        Some(_) => ammo_type2,
+        None => ammo_type1,
+    }
+    .and_then(|values| values.first().cloned())
+    .filter(|ammo_type| !ammo_type.is_empty());
+    states.primary_ammo = ammo_type1
+        .and_then(|values| values.first())
+        .filter(|ammo_type| !ammo_type.is_empty())
+        .map(|ammo_type| ammo_type.to_lowercase());
+    let ammo_use = property(actors, &actor.name, "weapon.ammouse1")
+        .or_else(|| property(actors, &actor.name, "weapon.ammouse"))
+        .and_then(|values| values.first()?.parse().ok())
+        .unwrap_or(1);
+    // the pack zooms its scoped weapons on altfire, aiming takes that over
+    let zoom = states
+        .frames
+        .iter()
+        .filter_map(|frame| frame.action.as_ref())
+        .filter(|action| action.name == "a_zoomfactor")
+        .filter_map(|action| action.arg_f32(0))
+        .fold(1.0, f32::max);
+    // weapons that light up the room when they fire get a muzzle flash
+    let muzzle_light = states
+        .frames
+        .iter()
+        .filter_map(|frame| frame.action.as_ref())
+        .any(|action| matches!(action.name.as_str(), "a_light1" | "a_light2" | "a_gunflash"))
+        .then(|| MUZZLE_FLASH_LIGHT.to_string());
+    // melee weapons have nothing to aim
+    let sights = if melee {
+        Sights::default()
+    } else if zoom > 1.0 {
+        Sights {
+            zoom,
+            scope: zoom >= SCOPE_ZOOM,
+            ..IRON_SIGHTS
+        }
+    } else {
+        IRON_SIGHTS
+    };
+
+    Ok(WeaponDef {
+        name: actor.name.clone(),
+        fire_modes: vec![FireMode {
+            trigger: if has_flag(actors, &actor.name, "noautofire") {
+                TriggerMode::Semi
+            } else {
+                TriggerMode::Auto
+            },
+            cooldown,
+            pellets: count.max(1) as u32,
+            // a count of 0 is a single perfectly accurate bullet, -1 a single one that always spreads
+            spread: if count == 0 { (0.0, 0.0) } else { spread },
+            pattern: SpreadPattern::Random,
+            light: None,
+            damage,
+            damage_type,
+            shot,
+        }],
+        ammo_type,
+        ammo_use,
+        magazine_size: magazine.unwrap_or(0),
+        // the states reload by themselves
+        reload: ReloadStyle::default(),
+        // A_SetPitch does the kicking
+        recoil: Recoil {
+            pattern: Vec::new(),
+            ..default()
+        },
+        sounds: WeaponSounds {
+            fire: fire_sound,
+            dry_fire,
+            reload,
+        },
+        sights,
+        muzzle_light,
+        states: Some(states),
+    })
+}
+
+/// `random(min, max)` rolled over and over comes to this
+fn average(min: i32, max: i32) -> f32 {
+    (min + max) as f32 / 2.0
+}
+
+/// How fast and hard an `A_FireProjectile` actor flies and hits, `None` without a `Speed`
+fn missile_def(actors: &HashMap<String, ParsedActor>, name: &str) -> Option<MissileDef> {
+    let speed = property(actors, name, "speed")?
+        .first()?
+        .parse::<f32>()
+        .ok()?;
+    // like doom a plain number is multiplied by 1 to 8, an expression in parentheses is used as is
+    let damage = property(actors, name, "damage")
+        .and_then(|values| values.first())
+        .and_then(|damage| {
+            match damage
+                .strip_prefix('(')
+                .and_then(|damage| damage.strip_suffix(')'))
+            {
+                Some(expression) => expression
+                    .split('+')
+                    .map(|term| punch_damage(term, average))
+                    .sum(),
+                None => damage.parse::<f32>().ok().map(|damage| damage * 4.5),
+            }
+        })
+        .unwrap_or_default();
+    Some(MissileDef {
+        speed: speed * TICS_PER_SECOND / MAP_UNITS_PER_METER,
+        damage,
+        damage_type: damage_type(actors, name).unwrap_or_default(),
+    })
+}
+
+/// An actor's own or inherited `DamageType`
+fn damage_type(actors: &HashMap<String, ParsedActor>, name: &str) -> Option<DamageType> {
+    let name = property(actors, name, "damagetype")?.first()?;
+    Some(DamageType::from_decorate(name.trim_matches('"')))
+}
+
+/// How a thrown actor flies and when and how hard it blows up,
+/// `None` for actors that never `A_Explode`
+fn explosive_def(
+    actors: &HashMap<String, ParsedActor>,
+    name: &str,
+    diagnostics: &mut Vec<DecorateDiagnostic>,
+) -> Option<ExplosiveDef> {
+    let number = |name_of: &str| {
+        property(actors, name, name_of).and_then(|values| values.first()?.parse::<f32>().ok())
+    };
+    let states = resolve_states(actors, name, 0, diagnostics);
+    let death = states.label("death")?;
+    let explode = reachable(&states, death).into_iter().find_map(|index| {
+        states.frames[index]
+            .action
+            .as_ref()
+            .filter(|action| action.name == "a_explode")
+    })?;
+    // zdoom's A_Explode falls back to the actor's ExplosionDamage and ExplosionRadius
+    let damage = explode
+        .arg_f32(0)
+        .filter(|damage| *damage > 0.0)
+        .or_else(|| number("explosiondamage"))
+        .unwrap_or(128.0);
+    let radius = explode
+        .arg_f32(1)
+        .filter(|radius| *radius > 0.0)
+        .or_else(|| number("explosionradius"))
+        .unwrap_or(damage);
+
+    // the fuse is however long the spawn states take to die,
+    // counting ReactionTime down with A_Countdown or running into Death
+    let mut countdown = number("reactiontime").unwrap_or(8.0) as i32;
+    let mut frame = states.label("spawn");
+    let mut tics = 0;
+    let mut fuse = None;
+    for _ in 0..MAX_FUSE_FRAMES {
+        let Some(index) = frame.filter(|index| *index < states.frames.len()) else {
+            break;
+        };
+        if index == death {
+            fuse = Some(tics);
+            break;
+        }
+        let state = &states.frames[index];
+        if state
+            .action
+            .as_ref()
+            .is_some_and(|action| action.name == "a_countdown")
+        {
+            countdown -= 1;
+            if countdown <= 0 {
+                fuse = Some(tics);
+                break;
+            }
+        }
+        if state.tics < 0 {
+            break;
+        }
+        tics += state.tics;
+        frame = match state.next {
+            StateNext::Next => Some(index + 1),
+            StateNext::Jump(target) => Some(target),
+            StateNext::Stop | StateNext::Wait => None,
+        };
+    }
+
+    let defaults = ExplosiveDef::default();
+    Some(ExplosiveDef {
+        speed: number("speed").map_or(defaults.speed, |speed| {
+            speed * TICS_PER_SECOND / MAP_UNITS_PER_METER
+        }),
+        fuse: fuse.map_or(defaults.fuse, |tics| tics as f32 / TICS_PER_SECOND),
+        gravity_scale: number("gravity").unwrap_or(1.0),
+        restitution: if has_flag(actors, name, "doombounce") {
+            number("bouncefactor").unwrap_or(0.7)
+        } else {
+            0.0
+        },
+        damage,
+        radius: radius / MAP_UNITS_PER_METER,
+    })
+}
+
+/// Frame indices reachable from `start` in breadth first order,
+/// through gotos and the labels and offsets jump actions can take
+fn reachable(states: &WeaponStates, start: usize) -> Vec<usize> {
+    let mut found = vec![start];
+    let mut cursor = 0;
+    while let Some(&index) = found.get(cursor) {
+        cursor += 1;
+        let Some(frame) = states.frames.get(index) else {
+            continue;
+        };
+        let mut targets = Vec::new();
+        match frame.next {
+            StateNext::Next => targets.push(index + 1),
+            StateNext::Jump(target) => targets.push(target),
+            StateNext::Stop | StateNext::Wait => {}
+        }
+        if let Some(action) = &frame.action {
+            let offsets_from = match action.name.as_str() {
+                "a_jump" | "a_jumpif" => 1,
+                "a_jumpifinventory" => 2,
+                _ => usize::MAX,
+            };
+            for (arg_index, arg) in action.args.iter().enumerate() {
+                if let Some(label) = action.arg_str(arg_index).and_then(|arg| states.label(arg)) {
+                    targets.push(label);
+                } else if arg_index >= offsets_from {
+                    targets.extend(arg.parse::<usize>().ok().map(|offset| index + offset));
+                }
+            }
+        }
+        for target in targets {
+            if target < states.frames.len() && !found.contains(&target) {
+                found.push(target);
+            }
+        }
+    }
+    found
+}
+
+fn property<'a>(
+    actors: &'a HashMap<String, ParsedActor>,
+    name: &str,
+    property: &str,
+) -> Option<&'a Vec<String>> {
+    let mut actor = actors.get(&name.to_lowercase());
+    // a handful of levels, inheritance loops are an error in the source anyway
+    for _ in 0..32 {
+        let current = actor?;
+        if let Some((_, values)) = current
+            .properties
+            .iter()
+            .rev()
+            .find(|(name, _)| name == property)
+        {
+            return Some(values);
+        }
+        actor = current
+            .parent
+            .as_ref()
+            .and_then(|parent| actors.get(&parent.to_lowercase()));
+    }
+    None
+}
+
+fn has_flag(actors: &HashMap<String, ParsedActor>, name: &str, flag: &str) -> bool {
+    let mut actor = actors.get(&name.to_lowercase());
+    for _ in 0..32 {
+        let Some(current) = actor else {
+            return false;
+        };
+        if let Some((_, set)) = current.flags.iter().rev().find(|(name, _)| name == flag) {
+            return *set;
+        }
+        actor = current
+            .parent
+            .as_ref()
+            .and_then(|parent| actors.get(&parent.to_lowercase()));
+    }
+    false
+}
+
+fn inherits(actors: &HashMap<String, ParsedActor>, name: &str, ancestor: &str) -> bool {
+    let mut name = name.to_lowercase();
+    for _ in 0..32 {
+        let Some(parent) = actors.get(&name).and_then(|actor| actor.parent.as_ref()) else {
+            return false;
+        };
+        name = parent.to_lowercase();
+        if name == ancestor {
+            return true;
+        }
+    }
+    false
+}
+
+/// Own frames after the parent's, own labels override the parent's, gotos resolved
+fn resolve_states(
+    actors: &HashMap<String, ParsedActor>,
+    name: &str,
+    depth: usize,
+    diagnostics: &mut Vec<DecorateDiagnostic>,
+) -> WeaponStates {
+    let Some(actor) = actors.get(name) else {
+        return WeaponStates::default();
+    };
+    let parent = match &actor.parent {
+        Some(parent) if depth < 32 => {
+            resolve_states(actors, &parent.to_lowercase(), depth + 1, diagnostics)
+        }
+        _ => WeaponStates::default(),
+    };
+
+    let base = parent.frames.len();
+    let mut states = parent.clone();
+    // states of different actors never fall into each other
+    if let Some(last) = states
+        .frames
+        .last_mut()
+        .filter(|last| last.next == StateNext::Next)
+    {
+        last.next = StateNext::Stop;
+    }
+    states
+        .frames
+        .extend(actor.frames.iter().cloned().map(|mut frame| {
+            if let StateNext::Jump(index) = frame.next {
+                frame.next = StateNext::Jump(base + index);
+            }
+            frame
+        }));
+    for (label, index) in &actor.labels {
+        states.labels.insert(label.clone(), base + index);
+    }
+
+    for goto in &actor.gotos {
+        let target = match goto.label.strip_prefix("super::") {
+            Some(label) => parent.labels.get(label),
+            None => states.labels.get(&goto.label),
+        };
+        let next = match target {
+            Some(index) => StateNext::Jump(index + goto.offset),
+            None => {
+                diagnostics.push(DecorateDiagnostic {
+                    file: actor.file.clone(),
+                    line: goto.line,
+                    message: format!("{}: goto unknown label `{}`", actor.name, goto.label),
+                });
+                StateNext::Stop
+            }
+        };
+        states.frames[base + goto.frame].next = next;
+    }
+    states
+}
+
+pub enum DecorateItem {
+    Include { path: String, line: usize },
+    Actor(Box<ParsedActor>),
+}
+
+/// An actor as written, before inheritance
+#[derive(Clone, Debug, Default)]
+pub struct ParsedActor {
+    pub name: String,
+    pub parent: Option<String>,
+    pub replaces: Option<String>,
+    pub file: String,
+    pub line: usize,
+    /// Lowercase property name and its values, strings unquoted
+    pub properties: Vec<(String, Vec<String>)>,
+    /// Lowercase flag name and whether it is set or cleared
+    pub flags: Vec<(String, bool)>,
+    /// Lowercase label to index into `frames`
+    pub labels: HashMap<String, usize>,
+    /// Jumps are to own frames, gotos are still pending in `gotos`
+    pub frames: Vec<StateFrame>,
+    pub gotos: Vec<PendingGoto>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PendingGoto {
+    pub frame: usize,
+    /// Lowercase, may start with `super::`
+    pub label: String,
+    pub offset: usize,
+    pub line: usize,
+}
+
+/// Parses one DECORATE lump. Anything it can't make sense of is reported and skipped,
+/// a broken actor doesn't take the rest of the file down with it.
+pub fn parse_decorate(
+    source: &str,
+    file: &str,
+    diagnostics: &mut Vec<DecorateDiagnostic>,
+) -> Vec<DecorateItem> {
+    let mut cursor = ScriptCursor::new(source);
+    let mut items = Vec::new();
+    let mut error = |line, message| {
+        diagnostics.push(DecorateDiagnostic {
+            file: file.to_string(),
+            line,
+            message,
+        })
+    };
+
+    loop {
+        cursor.skip_lines();
+        let line = cursor.line;
+        let Some(c) = cursor.peek() else {
+            break;
+        };
+        if c == b'#' {
+            cursor.bump();
+            let directive = cursor.token();
+            let rest = cursor.rest_of_line();
+            if directive.eq_ignore_ascii_case("include") {
+                items.push(DecorateItem::Include {
+                    path: rest.trim_matches('"').to_string(),
+                    line,
+                });
+            } else {
+                error(line, format!("unsupported directive `#{directive}`"));
+            }
+            continue;
+        }
+
+        let keyword = cursor.token();
+        if !keyword.eq_ignore_ascii_case("actor") {
+            error(line, format!("skipped unsupported top level `{keyword}`"));
+            cursor.skip_statement();
+            continue;
+        }
+        match parse_actor(&mut cursor, file) {
+            Ok(actor) => items.push(DecorateItem::Actor(Box::new(actor))),
+            Err((line, message)) => {
+                error(line, message);
+                cursor.skip_statement();
+            }
+        }
+    }
+    items
+}
+
+type ParseResult<T> = Result<T, (usize, String)>;
+
+fn parse_actor(cursor: &mut ScriptCursor, file: &str) -> ParseResult<ParsedActor> {
+    let mut actor = ParsedActor {
+        line: cursor.line,
+        name: cursor.token(),
+        file: file.to_string(),
+        ..default()
+    };
+    loop {
+        cursor.skip_lines();
+        match cursor.peek() {
+            Some(b'{') => break,
+            Some(b':') => {
+                cursor.bump();
+                actor.parent = Some(cursor.token());
+            }
+            Some(_) => {
+                let word = cursor.token();
+                if word.eq_ignore_ascii_case("replaces") {
+                    actor.replaces = Some(cursor.token());
+                } else if word.is_empty() {
+                    return Err((
+                        cursor.line,
+                        format!("unexpected `{}`", cursor.rest_of_line()),
+                    ));
+                }
+                // anything else is a doomednum or `native`
+            }
+            None => return Err((cursor.line, "actor without a body".to_string())),
+        }
+    }
+    cursor.bump();
+
+    loop {
+        cursor.skip_lines();
+        let line = cursor.line;
+        match cursor.peek() {
+            None => return Err((line, format!("{} is missing its closing brace", actor.name))),
+            Some(b'}') => {
+                cursor.bump();
+                return Ok(actor);
+            }
+            Some(sign @ (b'+' | b'-')) => {
+                cursor.bump();
+                actor
+                    .flags
+                    .push((cursor.token().to_lowercase(), sign == b'+'));
+            }
+            Some(_) => {
+                let name = cursor.token().to_lowercase();
+                match name.as_str() {
+                    "" => return Err((line, format!("unexpected `{}`", cursor.rest_of_line()))),
+                    "states" => {
+                        cursor.skip_lines();
+                        if !cursor.eat(b'{') {
+                            return Err((line, "expected `{` after States".to_string()));
+                        }
+                        parse_states(cursor, &mut actor)?;
+                    }
+                    "var" | "const" | "action" | "enum" => cursor.skip_statement(),
+                    _ => {
+                        let mut values = Vec::new();
+                        if !cursor.at_line_end() && cursor.peek() != Some(b'}') {
+                            values.push(cursor.value());
+                            while cursor.eat(b',') {
+                                cursor.skip_lines();
+                                values.push(cursor.value());
+                            }
+                        }
+                        actor.properties.push((name, values));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn parse_states(cursor: &mut ScriptCursor, actor: &mut ParsedActor) -> ParseResult<()> {
+    // start of the most recent label, for `loop`
+    let mut loop_start = 0;
+    // labels seen since the last frame, a flow keyword right after them needs a frame to live on
+    let mut dangling_label = false;
+
+    loop {
+        cursor.skip_lines();
+        let line = cursor.line;
+        match cursor.peek() {
+            None => return Err((line, "States is missing its closing brace".to_string())),
+            Some(b'}') => {
+                cursor.bump();
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let quoted = cursor.peek() == Some(b'"');
+        let first = cursor.token();
+        if !quoted && cursor.peek() == Some(b':') {
+            cursor.bump();
+            actor
+                .labels
+                .insert(first.to_lowercase(), actor.frames.len());
+            loop_start = actor.frames.len();
+            dangling_label = true;
+            continue;
+        }
+
+        let keyword = first.to_lowercase();
+        if !quoted && matches!(keyword.as_str(), "goto" | "loop" | "stop" | "fail" | "wait") {
+            if dangling_label || actor.frames.is_empty() {
+                actor.frames.push(StateFrame {
+                    sprite: "TNT1".to_string(),
+                    frame: 'A',
+                    tics: 0,
+                    bright: false,
+                    action: None,
+                    next: StateNext::Next,
+                    line,
+                });
+                dangling_label = false;
+            }
+            let frame = actor.frames.len() - 1;
+            let next = match keyword.as_str() {
+                "goto" => {
+                    let target = cursor.rest_of_line();
+                    let (label, offset) = match target.split_once('+') {
+                        Some((label, offset)) => (
+                            label.trim(),
+                            offset
+                                .trim()
+                                .parse()
+                                .map_err(|_| (line, format!("bad goto offset in `{target}`")))?,
+                        ),
+                        None => (target.trim(), 0),
+                    };
+                    actor.gotos.push(PendingGoto {
+                        frame,
+                        label: label.to_lowercase(),
+                        offset,
+                        line,
+                    });
+                    // filled in once labels are resolved
+                    StateNext::Stop
+                }
+                "loop" => StateNext::Jump(loop_start),
+                "wait" => StateNext::Wait,
+                _ => StateNext::Stop,
+            };
+            actor.frames[frame].next = next;
+            continue;
+        }
+
+        let frames = cursor.token();
+        let tics_token = cursor.token();
+        let tics = tics_token
+            .parse()
+            .map_err(|_| (line, format!("expected a tic count, found `{tics_token}`")))?;
+        let mut bright = false;
+        let mut action = None;
+        while !cursor.at_line_end() && cursor.peek() != Some(b'{') {
+            let word = cursor.token();
+            match word.to_lowercase().as_str() {
+                "" => return Err((line, format!("unexpected `{}`", cursor.rest_of_line()))),
+                "bright" => bright = true,
+                "fast" | "slow" | "nodelay" | "canraise" => {}
+                "offset" | "light" => {
+                    cursor.balanced();
+                }
+                name => {
+                    let args = if !cursor.at_line_end() && cursor.peek() == Some(b'(') {
+                        split_args(&cursor.balanced())
+                    } else {
+                        Vec::new()
+                    };
+                    action = Some(StateAction {
+                        name: name.to_string(),
+                        args,
+                    });
+                    cursor.rest_of_line();
+                    break;
+                }
+            }
+        }
+        if action.is_none() {
+            // an anonymous function block can follow on the same or the next line
+            let saved = cursor.clone();
+            cursor.skip_lines();
+            if cursor.peek() == Some(b'{') {
+                cursor.skip_statement();
+                action = Some(StateAction {
+                    name: ANONYMOUS_FUNCTION.to_string(),
+                    args: Vec::new(),
+                });
+            } else {
+                *cursor = saved;
+            }
+        }
+
+        for frame in frames.chars() {
+            actor.frames.push(StateFrame {
+                sprite: first.clone(),
+                frame,
+                tics,
+                bright,
+                action: action.clone(),
+                next: StateNext::Next,
+                line,
+            });
+        }
+        dangling_label = false;
+    }
+}
+
+/// Splits `(a, "b, c", f(d, e))` on its top level commas
+fn split_args(source: &str) -> Vec<String> {
+    let inner = source
+        .strip_prefix('(')
+        .and_then(|source| source.strip_suffix(')'))
+        .unwrap_or(source);
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut current = String::new();
+    for c in inner.chars() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                args.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        args.push(current.trim().to_string());
+    }
+    args
+}
+
+/// Logical sound names from SNDINFO
+#[derive(Default)]
+pub struct SoundInfo {
+    /// Lowercase logical name to lump
+    lumps: HashMap<String, String>,
+    /// Lowercase logical name to the logical names it picks from
+    random: HashMap<String, Vec<String>>,
+}
+
+impl SoundInfo {
+    pub fn parse(source: &str) -> Self {
+        let mut info = SoundInfo::default();
+        let mut cursor = ScriptCursor::new(source);
+        loop {
+            cursor.skip_lines();
+            let Some(c) = cursor.peek() else {
+                break;
+            };
+            if c == b'$' {
+                cursor.bump();
+                let directive = cursor.token().to_lowercase();
+                if directive == "alias" {
+                    let name = cursor.token().to_lowercase();
+                    info.random
+                        .insert(name, vec![cursor.token().to_lowercase()]);
+                    cursor.rest_of_line();
+                    continue;
+                }
+                if directive != "random" {
+                    // $volume, $rolloff, $playersound and friends only tune playback
+                    cursor.rest_of_line();
+                    continue;
+                }
+                let name = cursor.token().to_lowercase();
+                cursor.skip_lines();
+                if !cursor.eat(b'{') {
+                    cursor.rest_of_line();
+                    continue;
+                }
+                let mut choices = Vec::new();
+                loop {
+                    cursor.skip_lines();
+                    if cursor.peek().is_none() || cursor.eat(b'}') {
+                        break;
+                    }
+                    let choice = cursor.token().to_lowercase();
+                    if choice.is_empty() {
+                        // stray punctuation
+                        cursor.bump();
+                        continue;
+                    }
+                    choices.push(choice);
+                }
+                info.random.insert(name, choices);
+                continue;
+            }
+            let name = cursor.token().to_lowercase();
+            if name.is_empty() {
+                cursor.rest_of_line();
+                continue;
+            }
+            if !cursor.at_line_end() {
+                info.lumps.insert(name, cursor.token());
+            }
+            cursor.rest_of_line();
+        }
+        info
+    }
+
+    /// Asset paths a logical sound can play, more than one for `$random` sounds
+    fn resolve(&self, name: &str, files: &FileIndex) -> Vec<String> {
+        let mut paths = Vec::new();
+        let mut pending = vec![name.to_lowercase()];
+        // random sounds can nest, but not forever
+        for _ in 0..64 {
+            let Some(name) = pending.pop() else {
+                break;
+            };
+            if let Some(choices) = self.random.get(&name) {
+                pending.extend(choices.iter().cloned());
+                continue;
+            }
+            // DECORATE can also name a lump directly
+            let lump = self.lumps.get(&name).unwrap_or(&name);
+            paths.extend(files.sound(lump));
+        }
+        paths
+    }
+}
+
+/// `Sprite` definitions from TEXTURES, keyed by uppercase lump name
+fn parse_textures(source: &str, files: &FileIndex) -> HashMap<String, SpriteLayout> {
+    let mut sprites = HashMap::new();
+    let mut cursor = ScriptCursor::new(source);
+    loop {
+        cursor.skip_lines();
+        if cursor.peek().is_none() {
+            break;
+        }
+        let kind = cursor.token();
+        let name = cursor.token().to_uppercase();
+        let mut numbers = Vec::new();
+        while cursor.eat(b',') {
+            numbers.push(cursor.token().parse::<u32>().unwrap_or_default());
+        }
+        cursor.skip_lines();
+        if !cursor.eat(b'{') {
+            cursor.rest_of_line();
+            continue;
+        }
+
+        let mut layout = SpriteLayout {
+            size: (numbers.len() == 2).then(|| UVec2::new(numbers[0], numbers[1])),
+            offset: IVec2::ZERO,
+            scale: Vec2::ONE,
+            patches: Vec::new(),
+        };
+        loop {
+            cursor.skip_lines();
+            if cursor.peek().is_none() || cursor.eat(b'}') {
+                break;
+            }
+            let property = cursor.token().to_lowercase();
+            if property.is_empty() {
+                // stray punctuation
+                cursor.bump();
+                continue;
+            }
+            let mut values = vec![cursor.token()];
+            while cursor.eat(b',') {
+                values.push(cursor.token());
+            }
+            let int = |index: usize| {
+                values
+                    .get(index)
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_default()
+            };
+            let float = |index: usize| {
+                values
+                    .get(index)
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(1.0)
+            };
+            match property.as_str() {
+                "offset" => layout.offset = IVec2::new(int(0), int(1)),
+                "xscale" => layout.scale.x = float(0),
+                "yscale" => layout.scale.y = float(0),
+                "patch" | "graphic" => {
+                    if let Some(image) = files.image(&values[0]) {
+                        layout.patches.push(SpritePatch {
+                            image,
+                            position: IVec2::new(int(1), int(2)),
+                        });
+                    }
+                }
+                _ => {}
+            }
+            // patch options like FlipX live in their own block
+            cursor.skip_lines();
+            if cursor.peek() == Some(b'{') {
+                cursor.skip_statement();
+            }
+        }
+        if kind.eq_ignore_ascii_case("sprite") {
+            sprites.insert(name, layout);
+        }
+    }
+    sprites
+}
+
+/// The files of a pack, looked up the way ZDoom does, by lump name and case insensitive path
+struct FileIndex {
+    root: std::path::PathBuf,
+    /// Lowercase path relative to the pack to the real relative path
+    paths: HashMap<String, String>,
+    /// Uppercase file name without extension to relative paths, for lump lookups
+    lumps: HashMap<String, Vec<String>>,
+    asset_prefix: String,
+}
+
+impl FileIndex {
+    fn new(root: &Path, asset_prefix: &str) -> Self {
+        let mut index = FileIndex {
+            root: root.to_path_buf(),
+            paths: HashMap::new(),
+            lumps: HashMap::new(),
+            asset_prefix: asset_prefix.to_string(),
+        };
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let Ok(relative) = path.strip_prefix(root) else {
+                    continue;
+                };
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                index
+                    .paths
+                    .insert(relative.to_lowercase(), relative.clone());
+                if let Some(stem) = path.file_stem() {
+                    index
+                        .lumps
+                        .entry(stem.to_string_lossy().to_uppercase())
+                        .or_default()
+                        .push(relative);
+                }
+            }
+        }
+        index
+    }
+
+    /// Reads a file by case insensitive path, or by lump name for lumps like SNDINFO
+    fn read(&self, path: &str) -> Option<String> {
+        let relative = self.paths.get(&path.to_lowercase()).or_else(|| {
+            self.lumps
+                .get(&path.to_uppercase())
+                .and_then(|paths| paths.first())
+        })?;
+        let bytes = std::fs::read(self.root.join(relative)).ok()?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn lump_with_extension(&self, lump: &str, extensions: &[&str]) -> Option<String> {
+        let paths = self.lumps.get(&lump.to_uppercase())?;
+        // extensions are in order of preference
+        extensions.iter().find_map(|extension| {
+            paths
+                .iter()
+                .find(|path| path.to_lowercase().ends_with(extension))
+                .map(|path| format!("{}/{path}", self.asset_prefix))
+        })
+    }
+
+    fn image(&self, lump: &str) -> Option<String> {
+        self.lump_with_extension(lump, &[".png"])
+    }
+
+    fn sound(&self, lump: &str) -> Option<String> {
+        self.lump_with_extension(lump, &[".ogg", ".wav"])
+    }
+}
+
+/// Hand written scanner for ZDoom's text lumps, which are mostly line based.
+/// Shared by the DECORATE, SNDINFO and TEXTURES readers.
+#[derive(Clone)]
+pub(crate) struct ScriptCursor<'a> {
+    source: &'a str,
+    position: usize,
+    pub line: usize,
+}
+
+impl<'a> ScriptCursor<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            position: 0,
+            line: 1,
+        }
+    }
+
+    pub fn peek(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.source.as_bytes().get(self.position + offset).copied()
+    }
+
+    pub fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.position += 1;
+        if c == b'\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// Skips spaces and comments, stopping at a line break unless `lines`
+    fn skip(&mut self, lines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                b'\n' if !lines => return,
+                b' ' | b'\t' | b'\r' | b'\n' => {
+                    self.bump();
+                }
+                b'/' if self.peek_at(1) == Some(b'/') => {
+                    while self.peek().is_some_and(|c| c != b'\n') {
+                        self.bump();
+                    }
+                }
+                b'/' if self.peek_at(1) == Some(b'*') => {
+                    self.bump();
+                    self.bump();
+                    while self.peek().is_some()
+                        && !(self.peek() == Some(b'*') && self.peek_at(1) == Some(b'/'))
+                    {
+                        self.bump();
+                    }
+                    self.bump();
+                    self.bump();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    pub fn skip_lines(&mut self) {
+        self.skip(true);
+    }
+
+    pub fn at_line_end(&mut self) -> bool {
+        self.skip(false);
+        matches!(self.peek(), None | Some(b'\n'))
+    }
+
+    /// Consumes `c` if it comes next on this line
+    pub fn eat(&mut self, c: u8) -> bool {
+        self.skip(false);
+        let found = self.peek() == Some(c);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    /// A quoted string without its quotes, or a run of anything that isn't
+    /// whitespace or punctuation. Empty at the end of a line or before punctuation.
+    pub fn token(&mut self) -> String {
+        self.skip(false);
+        if self.peek() == Some(b'"') {
+            self.bump();
+            let start = self.position;
+            while self.peek().is_some_and(|c| c != b'"') {
+                self.bump();
+            }
+            let token = self.source[start..self.position].to_string();
+            self.bump();
+            return token;
+        }
+        let start = self.position;
+        while self.peek().is_some_and(|c| {
+            !c.is_ascii_whitespace() && !matches!(c, b'{' | b'}' | b'(' | b')' | b',' | b';' | b':')
+        }) && !(self.peek() == Some(b'/') && matches!(self.peek_at(1), Some(b'/' | b'*')))
+        {
+            self.bump();
+        }
+        self.source[start..self.position].to_string()
+    }
+
+    /// A token, or a parenthesised expression like `(random(25, 50))`
+    pub fn value(&mut self) -> String {
+        self.skip(false);
+        if self.peek() == Some(b'(') {
+            self.balanced()
+        } else {
+            self.token()
+        }
+    }
+
+    /// The rest of this line without comments, trimmed
+    pub fn rest_of_line(&mut self) -> String {
+        let mut rest = String::new();
+        loop {
+            self.skip(false);
+            match self.peek() {
+                None => break,
+                Some(b'\n') => {
+                    self.bump();
+                    break;
+                }
+                Some(_) => {
+                    let start = self.position;
+                    while self.peek().is_some_and(|c| {
+                        c != b'\n' && !(c == b'/' && matches!(self.peek_at(1), Some(b'/' | b'*')))
+                    }) {
+                        self.bump();
+                    }
+                    rest.push_str(&self.source[start..self.position]);
+                    rest.push(' ');
+                }
+            }
+        }
+        rest.trim().to_string()
+    }
+
+    /// A parenthesised group including the parentheses, may span lines
+    pub fn balanced(&mut self) -> String {
+        self.skip(false);
+        let start = self.position;
+        let mut depth = 0;
+        let mut in_string = false;
+        while let Some(c) = self.bump() {
+            match c {
+                b'"' => in_string = !in_string,
+                b'(' if !in_string => depth += 1,
+                b')' if !in_string => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+        self.source[start..self.position].to_string()
+    }
+
+    /// Skips to after the next `;`, or past the `{ }` block that comes first
+    pub fn skip_statement(&mut self) {
+        let mut depth = 0;
+        let mut in_string = false;
+        loop {
+            if !in_string {
+                self.skip(true);
+            }
+            let Some(c) = self.bump() else {
+                return;
+            };
+            match c {
+                b'"' => in_string = !in_string,
+                b';' if !in_string && depth == 0 => return,
+                b'{' if !in_string => depth += 1,
+                b'}' if !in_string => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actors(source: &str) -> (HashMap<String, ParsedActor>, Vec<DecorateDiagnostic>) {
+        let mut diagnostics = Vec::new();
+        let actors = parse_decorate(source, "DECORATE", &mut diagnostics)
+            .into_iter()
+            .filter_map(|item| match item {
+                DecorateItem::Actor(actor) => Some((actor.name.to_lowercase(), *actor)),
+                DecorateItem::Include { .. } => None,
+            })
+            .collect();
+        (actors, diagnostics)
+    }
+
+    fn pistol(fire: &str) -> FireMode {
+        let source = format!(
+            "actor TestPistol : Weapon
+            {{
+                Weapon.AmmoType \"Clip\"
+                States
+                {{
+                Ready:
+                    PISG A 1 A_WeaponReady
+                    Loop
+                Fire:
+                    PISG B 4 {fire}
+                    PISG C 4
+                    Goto Ready
+                }}
+            }}"
+        );
+        let (actors, _) = actors(&source);
+        let states = resolve_states(&actors, "testpistol", 0, &mut Vec::new());
+        let def = weapon_def(&actors, &actors["testpistol"], states).unwrap();
+        def.fire_modes[0].clone()
+    }
+
+    #[test]
+    fn minus_one_bullets_keep_their_spread() {
+        let mode = pistol("A_FireBullets(5.6, 0, -1, 5, \"BulletPuff\")");
+        assert_eq!(mode.pellets, 1);
+        assert_eq!(mode.spread, (5.6, 0.0));
+    }
+
+    #[test]
+    fn zero_bullets_are_accurate() {
+        let mode = pistol("A_FireBullets(5.6, 0, 0, 5)");
+        assert_eq!(mode.pellets, 1);
+        assert_eq!(mode.spread, (0.0, 0.0));
+    }
+
+    #[test]
+    fn pellets_and_negative_spread() {
+        let mode = pistol("A_FireBullets(-11.2, -7.1, 7, 5)");
+        assert_eq!(mode.pellets, 7);
+        assert_eq!(mode.spread, (11.2, 7.1));
+        assert_eq!(mode.damage, 5.0);
+        assert_eq!(mode.cooldown, 8.0 / TICS_PER_SECOND);
+    }
+
+    #[test]
+    fn custom_punches_import_as_melee() {
+        let mode = pistol("A_CustomPunch(random(3, 6) * 5, TRUE, 0, \"CrowbarPuff\", 90)");
+        assert_eq!(mode.pellets, 1);
+        assert_eq!(mode.spread, (0.0, 0.0));
+        assert_eq!(mode.damage, 22.5);
+        assert_eq!(
+            mode.shot,
+            ShotKind::Hitscan {
+                max_range: 90.0 / MAP_UNITS_PER_METER
+            }
+        );
+    }
+
+    #[test]
+    fn projectiles_fly_at_their_actors_speed() {
+        let (actors, _) = actors(
+            "actor TestBolt
+            {
+                Speed 150
+                Damage (random(15,30) + 70)
+            }
+            actor TestDart
+            {
+                Speed 35
+                Damage 4
+            }",
+        );
+        let bolt = missile_def(&actors, "testbolt").unwrap();
+        assert_eq!(bolt.speed, 150.0 * TICS_PER_SECOND / MAP_UNITS_PER_METER);
+        assert_eq!(bolt.damage, 92.5);
+        assert_eq!(missile_def(&actors, "testdart").unwrap().damage, 18.0);
+        assert!(missile_def(&actors, "missing").is_none());
+    }
+
+    #[test]
+    fn states_labels_and_gotos() {
+        let (actors, diagnostics) = actors(
+            "actor Base : Weapon
+            {
+                States
+                {
+                Ready:
+                    BASE A 1 A_WeaponReady
+                    Loop
+                }
+            }
+            actor Child : Base
+            {
+                +WEAPON.NOAUTOFIRE
+                States
+                {
+                Fire:
+                    CHLD AB 2 Bright
+                    Goto Super::Ready
+                }
+            }",
+        );
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(has_flag(&actors, "child", "weapon.noautofire"));
+        assert!(inherits(&actors, "child", "weapon"));
+
+        let states = resolve_states(&actors, "child", 0, &mut Vec::new());
+        let ready = states.label("ready").unwrap();
+        let fire = states.label("fire").unwrap();
+        assert_eq!(states.frames[ready].next, StateNext::Jump(ready));
+        assert_eq!(states.frames[fire].frame, 'A');
+        assert!(states.frames[fire].bright);
+        assert_eq!(states.frames[fire + 1].frame, 'B');
+        assert_eq!(states.frames[fire + 1].next, StateNext::Jump(ready));
+    }
+
+    #[test]
+    fn broken_actors_are_reported_and_skipped() {
+        let (actors, diagnostics) = actors(
+            "actor Broken : Weapon
+            {
+                States
+                {
+                Fire:
+                    PISG ; 4
+                }
+            }
+            , : ) ;
+            actor Fine : Weapon
+            {
+                Weapon.AmmoUse 2
+            }
+            actor Open : Weapon
+            {",
+        );
+        assert!(!diagnostics.is_empty());
+        assert!(!actors.contains_key("broken"));
+        assert!(!actors.contains_key("open"));
+        assert_eq!(
+            property(&actors, "fine", "weapon.ammouse"),
+            Some(&vec!["2".to_string()])
+        );
+    }
+
+    #[test]
+    fn sndinfo_random_skips_stray_punctuation() {
+        let info = SoundInfo::parse(
+            "weapons/fire DSPISTOL
+            $random weapons/ric { weapons/ric1, weapons/ric2 : }
+            weapons/ric1 RIC1
+            $volume weapons/ric1 0.5
+            ; stray",
+        );
+        assert_eq!(info.lumps["weapons/fire"], "DSPISTOL");
+        assert_eq!(info.lumps["weapons/ric1"], "RIC1");
+        assert_eq!(info.random["weapons/ric"], ["weapons/ric1", "weapons/ric2"]);
+    }
+
+    #[test]
+    fn textures_skip_stray_punctuation() {
+        let files = FileIndex::new(Path::new("missing-pack"), "pack");
+        let sprites = parse_textures(
+            "Sprite PISGA0, 60, 80
+            {
+                Offset -120, -100
+                XScale 0.5
+                Patch \"PISGA0\", 0, 0 ;
+                :
+            }
+            Graphic NOTASPRITE, 8, 8 { }
+            ,",
+            &files,
+        );
+        assert_eq!(sprites.len(), 1);
+        let layout = &sprites["PISGA0"];
+        assert_eq!(layout.size, Some(UVec2::new(60, 80)));
+        assert_eq!(layout.offset, IVec2::new(-120, -100));
+        assert_eq!(layout.scale, Vec2::new(0.5, 1.0));
+    }
+
+    #[test]
+    fn modkits_need_the_whole_block() {
+        let files = FileIndex::new(Path::new("missing-pack"), "pack");
+        let upgrades = parse_modkits(
+            "Script \"ModkitCheck\" (void)
+            {
+            if(checkweapon(\"Crowbar\"))
+            {
+            giveinventory(\"Crowbar2\",1);
+            takeinventory(\"crowbar\",1);
+            PlaySound(0,\"special/workbench\",CHAN_ITEM);
+            takeinventory(\"ItemModkit\",1);
+            Print(s:\"You installed the Cro-Hammer Head (PULVERISER MOD)!\");
+            setweapon(\"Crowbar2\");
+            terminate;
+            }
+            else if(checkweapon(\"Unfinished\"))
+            {
+            giveinventory(\"Unfinished2\",1);
+            terminate;
+            }
+            }",
+            &SoundInfo::default(),
+            &files,
+        );
+        assert_eq!(upgrades.len(), 1);
+        let crowbar = &upgrades[0];
+        assert_eq!(crowbar.from, "Crowbar");
+        assert_eq!(crowbar.to, "Crowbar2");
+        assert_eq!(crowbar.item, "ItemModkit");
+        assert_eq!(
+            crowbar.message,
+            "You installed the Cro-Hammer Head (PULVERISER MOD)!"
+        );
+        assert!(crowbar.sound.is_none());
+    }
+
+    #[test]
+    fn pack_crowbar_upgrades_into_the_cro_hammer() {
+        let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let import = import_decorate(&assets_dir, "AshesWeaponsV357", "DECORATE.TXT");
+        let imported = |name: &str| {
+            import
+                .weapons
+                .iter()
+                .any(|def| def.name.eq_ignore_ascii_case(name))
+        };
+        assert!(imported("Crowbar") && imported("Crowbar2"));
+        assert!(import.upgrades.iter().any(|upgrade| {
+            upgrade.from.eq_ignore_ascii_case("crowbar")
+                && upgrade.to.eq_ignore_ascii_case("crowbar2")
+        }));
+    }
+
+    #[test]
+    fn pack_napalm_gun_fires_its_custom_missile() {
+        let assets_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let import = import_decorate(&assets_dir, "AshesWeaponsV357", "DECORATE.TXT");
+        let napalm = import
+            .weapons
+            .iter()
+            .find(|def| def.name.eq_ignore_ascii_case("NapalmGun"))
+            .unwrap();
+        assert_eq!(
+            napalm.fire_modes[0].shot,
+            ShotKind::Projectile {
+                speed: 30.0 * TICS_PER_SECOND / MAP_UNITS_PER_METER
+            }
+        );
+        assert_eq!(napalm.fire_modes[0].damage, 85.0);
+        assert_eq!(napalm.fire_modes[0].damage_type, DamageType::Fire);
+        // the smoke puffed out with it is no shot
+        let missiles = &napalm.states.as_ref().unwrap().missiles;
+        assert!(missiles.contains_key("ashesfirebomb"));
+        assert!(!missiles.contains_key("gunsmokespawner"));
+    }
+}
@@ -0,0 +1,431 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    ControllerImpulse, DT, DamageEvent, DamageType, Damageable, GoldenController,
+    GoldenControllerInput, PlayerInventory, RenderPlayer, Weapon, WeaponDef, WeaponStateAction,
+    step_weapon_states,
+};
+
+/// Thrown explosives with fuses, and explosions with occluded radial damage, impulses and a flash
+pub struct ExplosivePlugin;
+
+impl Plugin for ExplosivePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Explosion>()
+            .add_event::<DamageEvent>()
+            .add_event::<ControllerImpulse>()
+            .init_resource::<ExplosiveAssets>()
+            .add_systems(
+                FixedUpdate,
+                (throw_grenades, wind_up_grenades, burn_fuses, explode)
+                    .chain()
+                    .after(step_weapon_states),
+            )
+            .add_systems(Update, fade_flashes);
+    }
+}
+
+/// Impulse in N·s at the centre of an explosion, per point of damage
+const IMPULSE_PER_DAMAGE: f32 = 4.0;
+
+/// Seconds of winding up for the hardest throw
+const FULL_WIND_UP: f32 = 1.0;
+
+/// Share of a grenade's full speed it flies at when thrown without winding up
+const MIN_THROW: f32 = 0.35;
+
+/// Seconds an explosion's light takes to fade out
+const FLASH_TIME: f32 = 0.3;
+
+/// Lumens of an explosion's light at its brightest
+const FLASH_INTENSITY: f32 = 4_000_000.0;
+
+/// Item the throw bind's grenades come out of, the pack's pipe bombs
+pub const GRENADE_AMMO: &str = "pipeammo";
+
+/// How a thrown explosive flies and blows up
+#[derive(Clone, Debug)]
+pub struct ExplosiveDef {
+    /// Throw speed in m/s
+    pub speed: f32,
+    /// Seconds from leaving the hand to the blast
+    pub fuse: f32,
+    pub gravity_scale: f32,
+    /// Bounciness
+    pub restitution: f32,
+    /// Damage at the centre, falling off to nothing at `radius`
+    pub damage: f32,
+    /// Metres
+    pub radius: f32,
+}
+
+/// The pack's `PipeBomb1`: 20 units a tic, 48 countdowns and `A_Explode(150, 256)`
+impl Default for ExplosiveDef {
+    fn default() -> Self {
+        Self {
+            speed: 21.9,
+            fuse: 2.5,
+            gravity_scale: 0.6,
+            restitution: 0.5,
+            damage: 150.0,
+            radius: 8.0,
+        }
+    }
+}
+
+/// Mesh and material every thrown explosive shares
+#[derive(Resource)]
+pub struct ExplosiveAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ExplosiveAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cylinder::new(0.04, 0.2));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::srgb(0.3, 0.3, 0.32),
+                perceptual_roughness: 0.6,
+                metallic: 0.7,
+                ..default()
+            });
+        Self { mesh, material }
+    }
+}
+
+/// Blows up on the next fixed tick
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Explosion {
+    pub position: Vec3,
+    /// Damage at the centre, falling off to nothing at `radius`
+    pub damage: f32,
+    /// Metres
+    pub radius: f32,
+    /// Whoever threw it
+    pub source: Option<Entity>,
+    /// The body that blew up, not in the way of its own blast
+    pub body: Option<Entity>,
+}
+
+/// A live explosive body, blows up when the fuse runs out
+#[derive(Component)]
+#[require(RigidBody::Dynamic)]
+pub struct Explosive {
+    /// Seconds left
+    pub fuse: f32,
+    pub damage: f32,
+    pub radius: f32,
+    pub thrower: Option<Entity>,
+}
+
+/// A grenade being wound up with the throw bind, the fuse is already burning
+#[derive(Component, Default)]
+pub struct GrenadeThrow {
+    /// Seconds the throw has been held, `None` when not throwing
+    pub held: Option<f32>,
+    /// The grenade being thrown
+    pub def: ExplosiveDef,
+}
+
+/// The light of an explosion, fading out
+#[derive(Component)]
+struct ExplosionFlash {
+    /// Seconds left
+    timer: f32,
+}
+
+/// Throws `def` from `origin`, with `fuse` seconds left on it
+fn spawn_explosive(
+    commands: &mut Commands,
+    assets: &ExplosiveAssets,
+    def: &ExplosiveDef,
+    thrower: Entity,
+    origin: Vec3,
+    velocity: Vec3,
+    fuse: f32,
+) {
+    let mut rng = rand::thread_rng();
+    let spin = Vec3::new(
+        rng.gen_range(-10.0..10.0),
+        rng.gen_range(-10.0..10.0),
+        rng.gen_range(-10.0..10.0),
+    );
+    commands.spawn((
+        Explosive {
+            fuse,
+            damage: def.damage,
+            radius: def.radius,
+            thrower: Some(thrower),
+        },
+        Collider::cylinder(0.04, 0.2),
+        Mass(0.5),
+        Mesh3d(assets.mesh.clone()),
+        MeshMaterial3d(assets.material.clone()),
+        Transform::from_translation(origin),
+        LinearVelocity(velocity),
+        AngularVelocity(spin),
+        GravityScale(def.gravity_scale),
+        Restitution::new(def.restitution),
+        SweptCcd::default(),
+    ));
+}
+
+/// System: `A_ThrowGrenade(actor, ...)` throws one of the weapon's `WeaponStates::thrown`
+#[allow(clippy::too_many_arguments)]
+fn throw_grenades(
+    mut commands: Commands,
+    assets: Res<ExplosiveAssets>,
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut state_actions: EventReader<WeaponStateAction>,
+    cameras: Query<(&GlobalTransform, &RenderPlayer), With<Camera3d>>,
+    mut players: Query<(&Weapon, &LinearVelocity, &mut PlayerInventory)>,
+) {
+    for WeaponStateAction { player, action } in state_actions.read() {
+        if action.name != "a_throwgrenade" {
+            continue;
+        }
+        let Some((global, _)) = cameras
+            .iter()
+            .find(|(_, render_player)| render_player.logical_entity == *player)
+        else {
+            continue;
+        };
+        let Ok((weapon, velocity, mut inventory)) = players.get_mut(*player) else {
+            continue;
+        };
+        let Some(def) = weapon_defs.get(&weapon.def) else {
+            continue;
+        };
+        let Some(states) = &def.states else {
+            continue;
+        };
+        let name = action.arg(0).unwrap_or_default().trim_matches('"');
+        let Some(thrown) = states.thrown.get(&name.to_lowercase()) else {
+            continue;
+        };
+        // useammo defaults to true
+        let use_ammo = action
+            .arg(4)
+            .is_none_or(|arg| !matches!(arg.to_lowercase().as_str(), "0" | "false"));
+        if let Some(ammo) = states.primary_ammo.as_ref().filter(|_| use_ammo) {
+            // like zdoom, nothing is thrown without the ammo for it
+            if inventory.count(ammo) < def.ammo_use {
+                continue;
+            }
+            inventory.take(ammo, def.ammo_use);
+        }
+        let forward = global.forward();
+        spawn_explosive(
+            &mut commands,
+            &assets,
+            thrown,
+            *player,
+            global.translation() + forward * 0.5,
+            forward * thrown.speed + velocity.0,
+            thrown.fuse,
+        );
+    }
+}
+
+/// System: hold the throw bind to wind up a grenade from the [`GRENADE_AMMO`] item,
+/// the longer the harder it flies, but the fuse is lit from the start
+#[allow(clippy::type_complexity)]
+fn wind_up_grenades(
+    mut commands: Commands,
+    assets: Res<ExplosiveAssets>,
+    mut explosions: EventWriter<Explosion>,
+    cameras: Query<(&GlobalTransform, &RenderPlayer), With<Camera3d>>,
+    mut players: Query<(
+        &GoldenControllerInput,
+        &LinearVelocity,
+        &mut PlayerInventory,
+        &mut GrenadeThrow,
+    )>,
+) {
+    for (global, render_player) in &cameras {
+        let player = render_player.logical_entity;
+        let Ok((input, velocity, mut inventory, mut throw)) = players.get_mut(player) else {
+            continue;
+        };
+        let Some(held) = throw.held else {
+            if input.throw && inventory.take(GRENADE_AMMO, 1) > 0 {
+                throw.held = Some(0.0);
+            }
+            continue;
+        };
+        let held = held + DT;
+        if held >= throw.def.fuse {
+            // held on to it for too long
+            throw.held = None;
+            explosions.write(Explosion {
+                position: global.translation(),
+                damage: throw.def.damage,
+                radius: throw.def.radius,
+                source: Some(player),
+                body: None,
+            });
+        } else if input.throw {
+            throw.held = Some(held);
+        } else {
+            throw.held = None;
+            let wind_up = (held / FULL_WIND_UP).min(1.0);
+            let speed = throw.def.speed * (MIN_THROW + (1.0 - MIN_THROW) * wind_up);
+            let forward = global.forward();
+            spawn_explosive(
+                &mut commands,
+                &assets,
+                &throw.def,
+                player,
+                global.translation() + forward * 0.5,
+                forward * speed + velocity.0,
+                throw.def.fuse - held,
+            );
+        }
+    }
+}
+
+/// System: explosives whose fuse ran out blow up
+fn burn_fuses(
+    mut commands: Commands,
+    mut explosions: EventWriter<Explosion>,
+    mut explosives: Query<(Entity, &mut Explosive, &GlobalTransform)>,
+) {
+    for (entity, mut explosive, global) in &mut explosives {
+        explosive.fuse -= DT;
+        if explosive.fuse > 0.0 {
+            continue;
+        }
+        explosions.write(Explosion {
+            position: global.translation(),
+            damage: explosive.damage,
+            radius: explosive.radius,
+            source: explosive.thrower,
+            body: Some(entity),
+        });
+        commands.entity(entity).despawn();
+    }
+}
+
+/// System: hurt and push everything in the blast radius the explosion can see,
+/// with damage and push falling off linearly like zdoom's `A_Explode`
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn explode(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    spatial_query: SpatialQuery,
+    mut explosions: EventReader<Explosion>,
+    mut damage: EventWriter<DamageEvent>,
+    mut impulses: EventWriter<ControllerImpulse>,
+    targets: Query<(&GlobalTransform, Option<&RigidBody>, Has<Damageable>)>,
+    players: Query<&GoldenController>,
+    mut external_impulses: Query<&mut ExternalImpulse>,
+    sensors: Query<(), With<Sensor>>,
+) {
+    for explosion in explosions.read() {
+        commands.spawn((
+            AudioPlayer::new(
+                asset_server.load::<AudioSource>("AshesWeaponsV357/Sounds/Effects/LEXP1.ogg"),
+            ),
+            PlaybackSettings::DESPAWN,
+        ));
+        commands.spawn((
+            ExplosionFlash { timer: FLASH_TIME },
+            PointLight {
+                color: Color::srgb(1.0, 0.7, 0.4),
+                intensity: FLASH_INTENSITY,
+                range: explosion.radius * 2.0,
+                ..default()
+            },
+            Transform::from_translation(explosion.position),
+        ));
+
+        let filter = SpatialQueryFilter::default().with_excluded_entities(explosion.body);
+        // whatever the blast starts inside of, like whoever held on to a grenade for too long,
+        // takes it but doesn't shield anyone else from it
+        let inside = spatial_query.point_intersections(explosion.position, &filter);
+        let occluders = SpatialQueryFilter::default()
+            .with_excluded_entities(explosion.body.into_iter().chain(inside));
+        let solid = |entity: Entity| !sensors.contains(entity);
+        let in_radius = spatial_query.shape_intersections(
+            &Collider::sphere(explosion.radius),
+            explosion.position,
+            Quat::IDENTITY,
+            &filter,
+        );
+        for entity in in_radius {
+            let Ok((global, body, damageable)) = targets.get(entity) else {
+                continue;
+            };
+            let dynamic = body.is_some_and(|body| body.is_dynamic());
+            if !dynamic && !damageable {
+                continue;
+            }
+            let offset = global.translation() - explosion.position;
+            let distance = offset.length();
+            let falloff = 1.0 - distance / explosion.radius;
+            if falloff <= 0.0 {
+                continue;
+            }
+            // walls and anything else in between take the blast
+            let direction = Dir3::new(offset).unwrap_or(Dir3::Y);
+            let blocked = spatial_query
+                .cast_ray_predicate(
+                    explosion.position,
+                    direction,
+                    distance,
+                    true,
+                    &occluders,
+                    &solid,
+                )
+                .is_some_and(|hit| hit.entity != entity);
+            if blocked {
+                continue;
+            }
+
+            if damageable {
+                damage.write(DamageEvent {
+                    target: entity,
+                    amount: explosion.damage * falloff,
+                    damage_type: DamageType::Explosion,
+                    source: explosion.source,
+                    hit_point: Some(global.translation()),
+                });
+            }
+            if !dynamic {
+                continue;
+            }
+            let impulse = direction * explosion.damage * IMPULSE_PER_DAMAGE * falloff;
+            if let Ok(controller) = players.get(entity) {
+                impulses.write(ControllerImpulse::new(entity, impulse / controller.mass));
+            } else if let Ok(mut external) = external_impulses.get_mut(entity) {
+                external.apply_impulse(impulse);
+            } else {
+                commands
+                    .entity(entity)
+                    .insert(ExternalImpulse::new(impulse));
+            }
+        }
+    }
+}
+
+/// System: explosion lights fade out and go away
+fn fade_flashes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flashes: Query<(Entity, &mut ExplosionFlash, &mut PointLight)>,
+) {
+    for (entity, mut flash, mut light) in &mut flashes {
+        flash.timer -= time.delta_secs();
+        if flash.timer <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        light.intensity = FLASH_INTENSITY * flash.timer / FLASH_TIME;
+    }
+}
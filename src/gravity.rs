@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::{GoldenControllerGravity, TriggerEnter, TriggerExit, TriggerFilter, TriggerVolume};
+
+pub struct GravityZonePlugin;
+
+impl Plugin for GravityZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, track_gravity_zones);
+    }
+}
+
+/// Which way a player falls, the controller treats the opposite direction as up
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum GravityField {
+    /// Whatever the avian `Gravity` resource says
+    #[default]
+    World,
+    /// Constant pull, for walking on walls and ceilings
+    Direction { down: Dir3, strength: f32 },
+    /// Pull towards a world space point, for spherical planetoids
+    Point { center: Vec3, strength: f32 },
+    /// No gravity at all, the controller switches to 6-DOF thruster movement
+    ZeroG,
+}
+
+impl GravityField {
+    /// Gravity acceleration at `position`
+    pub fn acceleration(&self, position: Vec3, world_gravity: Vec3) -> Vec3 {
+        match *self {
+            GravityField::World => world_gravity,
+            GravityField::Direction { down, strength } => down * strength,
+            GravityField::Point { center, strength } => {
+                (center - position).normalize_or_zero() * strength
+            }
+            GravityField::ZeroG => Vec3::ZERO,
+        }
+    }
+}
+
+/// Players inside this volume use its field instead of their own,
+/// the most recently entered zone wins when they overlap
+#[derive(Component)]
+#[require(TriggerVolume = TriggerVolume { filter: TriggerFilter::PLAYERS })]
+pub struct GravityZone {
+    pub field: GravityField,
+}
+
+fn track_gravity_zones(
+    mut enters: EventReader<TriggerEnter>,
+    mut exits: EventReader<TriggerExit>,
+    zones: Query<(), With<GravityZone>>,
+    mut players: Query<&mut GoldenControllerGravity>,
+) {
+    for enter in enters.read() {
+        if !zones.contains(enter.trigger) {
+            continue;
+        }
+        if let Ok(mut gravity) = players.get_mut(enter.entity) {
+            gravity.zones.retain(|zone| *zone != enter.trigger);
+            gravity.zones.push(enter.trigger);
+        }
+    }
+    for exit in exits.read() {
+        if let Ok(mut gravity) = players.get_mut(exit.entity) {
+            gravity.zones.retain(|zone| *zone != exit.trigger);
+        }
+    }
+}
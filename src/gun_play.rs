@@ -1,74 +1,400 @@
 use avian3d::prelude::*;
 
 use bevy::prelude::*;
+use serde::Deserialize;
 
+use crate::{
+    Accuracy, BallisticsPlugin, Carrier, DT, DamageType, FireBallistic, FireMode,
+    GoldenControllerInput, LaunchProjectile, PlayerInventory, ProjectilePlugin, RenderPlayer,
+    SpawnLight, TriggerMode, WEAPON_TOP, Weapon, WeaponDef, WeaponStateMachine, WeaponStatePlugin,
+};
+
+/// Fires the held weapon. Needs the [`WeaponStatePlugin`] to raise weapons before they fire,
+/// and the [`ProjectilePlugin`] and [`BallisticsPlugin`] to deliver those shots, in any order.
 pub struct GunPlayPlugin;
 
 impl Plugin for GunPlayPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, (shoot_bullet, despawn_bullet));
+        app.add_event::<ShotHit>()
+            .add_event::<LaunchProjectile>()
+            .add_event::<FireBallistic>()
+            .add_event::<SpawnLight>()
+            .add_systems(FixedUpdate, shoot_bullet);
+    }
+
+    fn finish(&self, app: &mut App) {
+        assert!(
+            app.is_plugin_added::<WeaponStatePlugin>(),
+            "GunPlayPlugin needs the WeaponStatePlugin, weapons never come up without it"
+        );
+        assert!(
+            app.is_plugin_added::<ProjectilePlugin>() && app.is_plugin_added::<BallisticsPlugin>(),
+            "GunPlayPlugin needs the ProjectilePlugin and BallisticsPlugin to deliver its shots"
+        );
     }
 }
 
-#[derive(Component)]
-pub struct Bullet {}
+/// How a fire mode delivers its shot
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ShotKind {
+    /// Instant ray cast from the camera, nothing past `max_range` is hit
+    Hitscan { max_range: f32 },
+    /// Physical bullet body, for slow rounds that should be dodgeable
+    Projectile { speed: f32 },
+    /// Ray marched round that drops, slows down and goes through thin surfaces
+    Ballistic {
+        /// Muzzle velocity in m/s
+        speed: f32,
+        /// Quadratic drag, see [`FireBallistic::drag`]
+        drag: f32,
+        max_range: f32,
+        /// Metres of stone it goes through
+        #[serde(default)]
+        penetration: f32,
+        #[serde(default)]
+        tracer: bool,
+    },
+}
+
+/// Where muzzle flashes light up, relative to the camera
+const MUZZLE_OFFSET: Vec3 = Vec3::new(0.2, -0.2, -0.8);
+
+/// Seconds a muzzle flash light lasts
+const MUZZLE_FLASH_TIME: f32 = 0.06;
 
-/// System: when left mouse is clicked, spawn a bullet
-fn shoot_bullet(
+/// A hitscan shot or a projectile hit something
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ShotHit {
+    /// The logical player who fired
+    pub shooter: Entity,
+    /// The collider that was hit
+    pub entity: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Which way the shot was going
+    pub direction: Dir3,
+    pub distance: f32,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    /// Which trigger pull it came from, the pellets of one shot share it
+    pub shot: u32,
+}
+
+/// System: fire each player's weapon from their camera, following its current fire mode,
+/// or for sprite driven weapons whatever their states asked for this tick
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn shoot_bullet(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    query: Query<&GlobalTransform, With<Camera3d>>,
+    asset_server: Res<AssetServer>,
+    weapon_defs: Res<Assets<WeaponDef>>,
+    spatial_query_pipeline: Res<SpatialQueryPipeline>,
+    mut shot_hits: EventWriter<ShotHit>,
+    mut launches: EventWriter<LaunchProjectile>,
+    mut ballistic_shots: EventWriter<FireBallistic>,
+    mut lights: EventWriter<SpawnLight>,
+    mut shot_count: Local<u32>,
+    colliders: Query<(&Collider, &Position, &Rotation, Option<&CollisionLayers>)>,
+    query: Query<(Entity, &GlobalTransform, &RenderPlayer), With<Camera3d>>,
+    mut input_query: Query<(
+        &mut GoldenControllerInput,
+        &mut Weapon,
+        &mut Accuracy,
+        &mut WeaponStateMachine,
+        Option<&Carrier>,
+        Option<&mut PlayerInventory>,
+    )>,
 ) {
-    if mouse.pressed(MouseButton::Left) {
-        if let Ok(global) = query.single() {
-            println!("OKAY");
-            // Bullet spawn position = in front of player
-            let forward = global.forward();
-            let spawn_pos = global.translation() + forward * 1.0; // 1 unit in front
-
-            // Bullet speed
-            let speed = 400.0;
-            // First, create an emissive material
-            let emissive_material = materials.add(StandardMaterial {
-                base_color: Color::srgb(0.8, 0.7, 0.6),
-                emissive: LinearRgba::new(0.4, 0.3, 0.2, 0.1), // Glow color (usually darker than base)
-                perceptual_roughness: 0.1,
-                metallic: 0.8,
-                ..default()
-            });
-
-            commands.spawn((
-                // Small sphere collider
-                Collider::sphere(0.001),
-                Bullet {},
-                Mesh3d(meshes.add(Sphere::new(0.01))),
-                MeshMaterial3d(emissive_material),
-                RigidBody::Dynamic,
-                Mass(0.001),
-                SpeculativeMargin::ZERO,
-                // Spawn at player position
-                Transform::from_translation(spawn_pos),
-                LinearVelocity(forward * speed),
-                // Optional: disable gravity if you want straight shot
-                GravityScale(1.0),
-                SweptCcd::default(),
-                // Optional: frictionless
-                Friction::new(0.1),
-                Restitution::new(0.6),
-                LinearDamping(0.01),
+    let mut rng = rand::thread_rng();
+    for (camera, global, render_player) in query.iter() {
+        let Ok((mut input, mut weapon, mut accuracy, mut machine, carrier, inventory)) =
+            input_query.get_mut(render_player.logical_entity)
+        else {
+            continue;
+        };
+        weapon.cooldown = (weapon.cooldown - DT).max(0.0);
+        let Some(def) = weapon_defs.get(&weapon.def) else {
+            continue;
+        };
+        let Some(mode) = def.fire_modes.get(weapon.mode) else {
+            continue;
+        };
+
+        // sprite driven weapons fire from their states, which also play their own sounds
+        let shots = if def.states.is_some() {
+            std::mem::take(&mut machine.shots)
+        } else {
+            if weapon.reload.is_some() || machine.y > WEAPON_TOP {
+                continue;
+            }
+            // fire throws whatever is carried instead
+            let pressed = input.fire && !carrier.is_some_and(Carrier::blocks_fire);
+            if !pull_trigger(&mut weapon, mode, pressed) {
+                continue;
+            }
+            // players without an inventory have bottomless pockets
+            let has_ammo = inventory.is_none_or(|mut inventory| def.take_ammo(&mut inventory));
+            let sound = if has_ammo {
+                &def.sounds.fire
+            } else {
+                &def.sounds.dry_fire
+            };
+            if let Some(sound) = sound {
+                commands.spawn((
+                    AudioPlayer::new(asset_server.load::<AudioSource>(sound)),
+                    PlaybackSettings::DESPAWN,
+                ));
+            }
+            if !has_ammo {
+                weapon.burst_left = 0;
+                continue;
+            }
+            vec![mode.clone()]
+        };
+
+        if let Some(light) = def.muzzle_light.as_ref().filter(|_| !shots.is_empty()) {
+            lights.write(SpawnLight::flash(
+                light,
+                camera,
+                MUZZLE_OFFSET,
+                MUZZLE_FLASH_TIME,
             ));
         }
+        let origin = global.translation();
+        let filter =
+            SpatialQueryFilter::default().with_excluded_entities([render_player.logical_entity]);
+        for shot in &shots {
+            *shot_count = shot_count.wrapping_add(1);
+            let spread = accuracy.spread(shot.spread);
+            accuracy.fire(&def.recoil, &mut input);
+            // every pellet's direction up front, so hitscan pellets share one tree query
+            let directions: Vec<Dir3> = (0..shot.pellets)
+                .map(|pellet| {
+                    let (yaw, pitch) = shot.pattern.offset(pellet, shot.pellets, spread, &mut rng);
+                    let spread =
+                        Quat::from_euler(EulerRot::YXZ, yaw.to_radians(), pitch.to_radians(), 0.0);
+                    Dir3::new_unchecked(global.rotation() * spread * Vec3::NEG_Z)
+                })
+                .collect();
+            let shooter = render_player.logical_entity;
+            let id = *shot_count;
+            match shot.shot {
+                ShotKind::Hitscan { max_range } => {
+                    let hits = cast_pellets(
+                        &spatial_query_pipeline,
+                        &colliders,
+                        origin,
+                        &directions,
+                        max_range,
+                        &filter,
+                    );
+                    shot_hits.write_batch(hits.into_iter().map(|(direction, hit)| ShotHit {
+                        shooter,
+                        entity: hit.entity,
+                        point: origin + direction * hit.distance,
+                        normal: hit.normal,
+                        direction,
+                        distance: hit.distance,
+                        damage: shot.damage,
+                        damage_type: shot.damage_type,
+                        shot: id,
+                    }));
+                }
+                ShotKind::Projectile { speed } => {
+                    launches.write_batch(directions.into_iter().map(|direction| {
+                        LaunchProjectile {
+                            shooter,
+                            origin,
+                            direction,
+                            speed,
+                            damage: shot.damage,
+                            damage_type: shot.damage_type,
+                            shot: id,
+                            light: shot.light.clone(),
+                        }
+                    }));
+                }
+                ShotKind::Ballistic {
+                    speed,
+                    drag,
+                    max_range,
+                    penetration,
+                    tracer,
+                } => {
+                    ballistic_shots.write_batch(directions.into_iter().map(|direction| {
+                        FireBallistic {
+                            shooter,
+                            origin,
+                            direction,
+                            speed,
+                            drag,
+                            max_range,
+                            penetration,
+                            tracer,
+                            damage: shot.damage,
+                            damage_type: shot.damage_type,
+                            shot: id,
+                        }
+                    }));
+                }
+            }
+        }
     }
 }
 
-/// System: when left mouse is clicked, spawn a bullet
-fn despawn_bullet(mut commands: Commands, query: Query<(Entity, &LinearVelocity), With<Bullet>>) {
-    for (e, v) in query.iter() {
-        if v.length() < 50.0 {
-            commands.entity(e).despawn();
-            println!("DESPAWMD");
+/// The closest hit of every hitscan pellet. A lone pellet casts its ray, a spread walks the tree
+/// once for the box around all of its rays and each pellet only tests the colliders in it.
+fn cast_pellets(
+    spatial_query_pipeline: &SpatialQueryPipeline,
+    colliders: &Query<(&Collider, &Position, &Rotation, Option<&CollisionLayers>)>,
+    origin: Vec3,
+    directions: &[Dir3],
+    max_range: f32,
+    filter: &SpatialQueryFilter,
+) -> Vec<(Dir3, RayHitData)> {
+    if let [direction] = directions {
+        return spatial_query_pipeline
+            .cast_ray(origin, *direction, max_range, true, filter)
+            .map(|hit| (*direction, hit))
+            .into_iter()
+            .collect();
+    }
+    let bounds = directions.iter().fold(
+        ColliderAabb::from_min_max(origin, origin),
+        |bounds, direction| {
+            let end = origin + *direction * max_range;
+            bounds.merged(ColliderAabb::from_min_max(end, end))
+        },
+    );
+    let candidates: Vec<_> = spatial_query_pipeline
+        .aabb_intersections_with_aabb(bounds)
+        .into_iter()
+        .filter_map(|entity| {
+            let (collider, position, rotation, layers) = colliders.get(entity).ok()?;
+            filter
+                .test(entity, layers.copied().unwrap_or_default())
+                .then_some((entity, collider, *position, *rotation))
+        })
+        .collect();
+    directions
+        .iter()
+        .filter_map(|direction| {
+            candidates
+                .iter()
+                .filter_map(|(entity, collider, position, rotation)| {
+                    let (distance, normal) = collider.cast_ray(
+                        *position,
+                        *rotation,
+                        origin,
+                        direction.as_vec3(),
+                        max_range,
+                        true,
+                    )?;
+                    Some(RayHitData {
+                        entity: *entity,
+                        distance,
+                        normal,
+                    })
+                })
+                .min_by(|a, b| a.distance.total_cmp(&b.distance))
+                .map(|hit| (*direction, hit))
+        })
+        .collect()
+}
+
+/// Semi, burst and auto triggers for weapons without states, true when a shot goes off
+fn pull_trigger(weapon: &mut Weapon, mode: &FireMode, pressed: bool) -> bool {
+    let pulled = pressed && !weapon.trigger_held;
+    weapon.trigger_held = pressed;
+    weapon.burst_left = match mode.trigger {
+        TriggerMode::Burst { count } if pulled && weapon.burst_left == 0 => count,
+        _ => weapon.burst_left,
+    };
+    let wants_to_fire = match mode.trigger {
+        TriggerMode::Semi => pulled,
+        TriggerMode::Burst { .. } => weapon.burst_left > 0,
+        TriggerMode::Auto => pressed,
+    };
+    if !wants_to_fire || weapon.cooldown > 0.0 {
+        return false;
+    }
+    weapon.cooldown = mode.cooldown;
+    weapon.burst_left = weapon.burst_left.saturating_sub(1);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn batched_pellets_hit_what_lone_rays_hit() {
+        let mut world = World::new();
+        let layers = CollisionLayers::default();
+        let rotation = Rotation::default();
+        let boxes: Vec<_> = [Vec3::new(0.0, 0.0, -5.0), Vec3::new(2.0, 0.0, -10.0)]
+            .into_iter()
+            .map(|at| {
+                (
+                    world.spawn_empty().id(),
+                    Position(at),
+                    Collider::cuboid(1.0, 1.0, 1.0),
+                )
+            })
+            .collect();
+        let mut pipeline = SpatialQueryPipeline::new();
+        pipeline.update(
+            boxes.iter().map(|(entity, position, collider)| {
+                (*entity, position, &rotation, collider, &layers)
+            }),
+        );
+        for (entity, position, collider) in &boxes {
+            world
+                .entity_mut(*entity)
+                .insert((*position, rotation, collider.clone()));
         }
+
+        let directions = [
+            Vec3::NEG_Z,
+            Vec3::new(-0.05, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, -5.0),
+            Vec3::new(0.0, 1.0, -1.0),
+        ]
+        .map(|direction| Dir3::new(direction).unwrap());
+        let filter = SpatialQueryFilter::default();
+        let lone: Vec<_> = directions
+            .iter()
+            .filter_map(|direction| {
+                pipeline
+                    .cast_ray(Vec3::ZERO, *direction, 50.0, true, &filter)
+                    .map(|hit| (*direction, hit))
+            })
+            .collect();
+        let batched = world
+            .run_system_once(
+                move |colliders: Query<(
+                    &Collider,
+                    &Position,
+                    &Rotation,
+                    Option<&CollisionLayers>,
+                )>| {
+                    cast_pellets(
+                        &pipeline,
+                        &colliders,
+                        Vec3::ZERO,
+                        &directions,
+                        50.0,
+                        &filter,
+                    )
+                },
+            )
+            .unwrap();
+
+        // two pellets hit the near box, one goes wide to the far one and one over both
+        assert_eq!(lone.len(), 3);
+        assert_eq!(lone[0].1.entity, boxes[0].0);
+        assert_eq!(lone[2].1.entity, boxes[1].0);
+        assert_eq!(batched, lone);
     }
 }
@@ -0,0 +1,418 @@
+use std::collections::{HashMap, VecDeque};
+
+use avian3d::prelude::*;
+use bevy::asset::RenderAssetUsages;
+use bevy::core_pipeline::prepass::DepthPrepass;
+use bevy::pbr::decal::{ForwardDecal, ForwardDecalMaterial, ForwardDecalMaterialExt};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::{Damageable, MAP_UNITS_PER_METER, RenderPlayer, ShotHit, TICS_PER_SECOND};
+
+/// Puffs, decals and ricochet sounds where bullets hit, varied by [`SurfaceMaterial`]
+pub struct ImpactPlugin;
+
+impl Plugin for ImpactPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BulletImpact>()
+            .init_resource::<ImpactEffects>()
+            .init_resource::<LiveDecals>()
+            .add_systems(
+                Update,
+                (
+                    prepare_decal_cameras,
+                    (shot_impacts, spawn_impacts, animate_puffs).chain(),
+                ),
+            );
+    }
+}
+
+/// What a collider is made of. Colliders without one are stone, or flesh if they can be hurt.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum SurfaceMaterial {
+    #[default]
+    Stone,
+    Metal,
+    Wood,
+    Flesh,
+}
+
+impl SurfaceMaterial {
+    /// What a collider is made of, falling back like [`SurfaceMaterial`] says
+    pub fn of(
+        entity: Entity,
+        surfaces: &Query<&SurfaceMaterial>,
+        damageables: &Query<(), With<Damageable>>,
+    ) -> Self {
+        surfaces.get(entity).copied().unwrap_or_else(|_| {
+            if damageables.contains(entity) {
+                SurfaceMaterial::Flesh
+            } else {
+                SurfaceMaterial::Stone
+            }
+        })
+    }
+
+    /// Penetration a round spends per metre of this, relative to stone
+    pub fn penetration_resistance(self) -> f32 {
+        match self {
+            SurfaceMaterial::Stone => 1.0,
+            SurfaceMaterial::Metal => 3.0,
+            SurfaceMaterial::Wood => 0.25,
+            SurfaceMaterial::Flesh => 0.2,
+        }
+    }
+}
+
+/// A bullet hit something, hitscan or projectile
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BulletImpact {
+    /// The collider that was hit
+    pub entity: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Which way the bullet was going
+    pub direction: Dir3,
+}
+
+/// How hits on one kind of surface look and sound
+#[derive(Clone)]
+pub struct ImpactStyle {
+    /// Puff sprite frames, each with its size in metres and how long it shows in seconds
+    pub puff: Vec<(Handle<StandardMaterial>, Vec2, f32)>,
+    /// `None` leaves no mark
+    pub decal: Option<Handle<ForwardDecalMaterial<StandardMaterial>>>,
+    /// Metres across
+    pub decal_size: f32,
+    /// Asset paths, one is picked at random
+    pub sounds: Vec<String>,
+    /// Chance a hit makes a sound at all
+    pub sound_chance: f32,
+}
+
+/// Impact styles by surface, materials are shared by every puff and decal of a style
+#[derive(Resource)]
+pub struct ImpactEffects {
+    pub styles: HashMap<SurfaceMaterial, ImpactStyle>,
+    /// Oldest decals are removed past this many
+    pub max_decals: usize,
+    /// Unit quad every puff is drawn on
+    pub quad: Handle<Mesh>,
+}
+
+impl FromWorld for ImpactEffects {
+    fn from_world(world: &mut World) -> Self {
+        let chip = world.resource_mut::<Assets<Image>>().add(bullet_chip());
+        let quad = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Rectangle::new(1.0, 1.0));
+        let asset_server = world.resource::<AssetServer>().clone();
+
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        // HitPuff's dust and its ricochet sparks, with their tics and pixel sizes
+        let dust = puff_frames(
+            &mut materials,
+            &asset_server,
+            "CPUF",
+            &[
+                ('A', 1, Vec2::new(20.0, 21.0)),
+                ('B', 1, Vec2::new(28.0, 32.0)),
+                ('C', 3, Vec2::new(38.0, 41.0)),
+                ('D', 3, Vec2::new(51.0, 37.0)),
+                ('E', 3, Vec2::new(53.0, 44.0)),
+            ],
+            false,
+        );
+        let sparks = puff_frames(
+            &mut materials,
+            &asset_server,
+            "PIN2",
+            &[
+                ('B', 1, Vec2::new(4.0, 3.0)),
+                ('C', 1, Vec2::new(9.0, 7.0)),
+                ('D', 3, Vec2::new(15.0, 10.0)),
+                ('E', 3, Vec2::new(19.0, 17.0)),
+                ('F', 3, Vec2::new(26.0, 23.0)),
+                ('G', 3, Vec2::new(26.0, 13.0)),
+                ('H', 3, Vec2::new(2.0, 14.0)),
+            ],
+            true,
+        );
+        let splinters = dust[..3].to_vec();
+
+        let mut decals = world.resource_mut::<Assets<ForwardDecalMaterial<StandardMaterial>>>();
+        let mut decal = |tint: Color| {
+            decals.add(ForwardDecalMaterial {
+                base: StandardMaterial {
+                    base_color: tint,
+                    base_color_texture: Some(chip.clone()),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                },
+                extension: ForwardDecalMaterialExt {
+                    depth_fade_factor: 1.0,
+                },
+            })
+        };
+        let ricochets = (1..=6)
+            .map(|n| format!("AshesWeaponsV357/Sounds/Effects/DSRICOT{n}.ogg"))
+            .collect::<Vec<_>>();
+
+        let styles = HashMap::from([
+            (
+                SurfaceMaterial::Stone,
+                ImpactStyle {
+                    puff: dust,
+                    decal: Some(decal(Color::WHITE)),
+                    decal_size: 0.12,
+                    sounds: ricochets.clone(),
+                    sound_chance: 0.5,
+                },
+            ),
+            (
+                SurfaceMaterial::Metal,
+                ImpactStyle {
+                    puff: sparks,
+                    decal: Some(decal(Color::srgb(0.6, 0.6, 0.65))),
+                    decal_size: 0.08,
+                    sounds: ricochets,
+                    sound_chance: 1.0,
+                },
+            ),
+            (
+                SurfaceMaterial::Wood,
+                ImpactStyle {
+                    puff: splinters,
+                    decal: Some(decal(Color::srgb(0.45, 0.3, 0.2))),
+                    decal_size: 0.1,
+                    sounds: Vec::new(),
+                    sound_chance: 0.0,
+                },
+            ),
+            (
+                SurfaceMaterial::Flesh,
+                ImpactStyle {
+                    puff: Vec::new(),
+                    decal: None,
+                    decal_size: 0.0,
+                    sounds: Vec::new(),
+                    sound_chance: 0.0,
+                },
+            ),
+        ]);
+        Self {
+            styles,
+            max_decals: 256,
+            quad,
+        }
+    }
+}
+
+/// Materials for a pack sprite's frames, half size like the pack's smaller puffs
+fn puff_frames(
+    materials: &mut Assets<StandardMaterial>,
+    asset_server: &AssetServer,
+    sprite: &str,
+    frames: &[(char, u32, Vec2)],
+    bright: bool,
+) -> Vec<(Handle<StandardMaterial>, Vec2, f32)> {
+    frames
+        .iter()
+        .map(|(frame, tics, pixels)| {
+            let image = format!("AshesWeaponsV357/Sprites/Effects/{sprite}{frame}0.png");
+            let material = materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load(image)),
+                alpha_mode: AlphaMode::Blend,
+                unlit: bright,
+                cull_mode: None,
+                ..default()
+            });
+            let size = *pixels * 0.5 / MAP_UNITS_PER_METER;
+            (material, size, *tics as f32 / TICS_PER_SECOND)
+        })
+        .collect()
+}
+
+/// Stands in for GZDoom's built-in `BulletChip`, which the pack uses but doesn't ship
+fn bullet_chip() -> Image {
+    const SIZE: u32 = 16;
+    let data = (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let offset = Vec2::new((i % SIZE) as f32, (i / SIZE) as f32) + 0.5;
+            let distance = offset.distance(Vec2::splat(SIZE as f32 / 2.0)) / (SIZE as f32 / 2.0);
+            let alpha = ((1.0 - distance) * 2.0).clamp(0.0, 1.0);
+            let shade = (distance * 60.0) as u8;
+            [shade, shade, shade, (alpha * 255.0) as u8]
+        })
+        .collect();
+    Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// A puff playing through its style's frames
+#[derive(Component)]
+struct ImpactPuff {
+    surface: SurfaceMaterial,
+    frame: usize,
+    /// Seconds left on the frame
+    timer: f32,
+}
+
+/// Decals in the order they were made, the oldest go first once there are too many
+#[derive(Resource, Default)]
+pub(crate) struct LiveDecals(VecDeque<Entity>);
+
+/// Spawns a decal on `entity`, following it if it moves, and removes the oldest past the cap
+pub(crate) fn spawn_decal(
+    commands: &mut Commands,
+    live_decals: &mut LiveDecals,
+    max_decals: usize,
+    material: Handle<ForwardDecalMaterial<StandardMaterial>>,
+    transform: Transform,
+    entity: Entity,
+    bodies: &Query<(&RigidBody, &GlobalTransform)>,
+) {
+    let mut decal = commands.spawn((ForwardDecal, MeshMaterial3d(material), transform));
+    // marks on things that move go along with them
+    if let Some((_, global)) = bodies
+        .get(entity)
+        .ok()
+        .filter(|(body, _)| !body.is_static())
+    {
+        decal.insert((
+            GlobalTransform::from(transform).reparented_to(global),
+            ChildOf(entity),
+        ));
+    }
+    live_decals.0.push_back(decal.id());
+    while live_decals.0.len() > max_decals {
+        let Some(oldest) = live_decals.0.pop_front() else {
+            break;
+        };
+        if let Ok(mut oldest) = commands.get_entity(oldest) {
+            oldest.try_despawn();
+        }
+    }
+}
+
+/// System: forward decals only draw on cameras with a depth prepass
+fn prepare_decal_cameras(mut commands: Commands, cameras: Query<Entity, Added<RenderPlayer>>) {
+    for camera in &cameras {
+        commands.entity(camera).insert(DepthPrepass);
+    }
+}
+
+fn shot_impacts(mut shot_hits: EventReader<ShotHit>, mut impacts: EventWriter<BulletImpact>) {
+    for hit in shot_hits.read() {
+        impacts.write(BulletImpact {
+            entity: hit.entity,
+            point: hit.point,
+            normal: hit.normal,
+            direction: hit.direction,
+        });
+    }
+}
+
+/// System: puff, decal and ricochet for every impact
+#[allow(clippy::too_many_arguments)]
+fn spawn_impacts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    effects: Res<ImpactEffects>,
+    mut impacts: EventReader<BulletImpact>,
+    mut live_decals: ResMut<LiveDecals>,
+    surfaces: Query<&SurfaceMaterial>,
+    damageables: Query<(), With<Damageable>>,
+    bodies: Query<(&RigidBody, &GlobalTransform)>,
+) {
+    let mut rng = rand::thread_rng();
+    for impact in impacts.read() {
+        let surface = SurfaceMaterial::of(impact.entity, &surfaces, &damageables);
+        let Some(style) = effects.styles.get(&surface) else {
+            continue;
+        };
+
+        if let Some((material, size, seconds)) = style.puff.first() {
+            // faces back along the shot, like a sprite facing whoever fired
+            commands.spawn((
+                ImpactPuff {
+                    surface,
+                    frame: 0,
+                    timer: *seconds,
+                },
+                Mesh3d(effects.quad.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::from_translation(impact.point + impact.normal * 0.05)
+                    .looking_to(impact.direction, Vec3::Y)
+                    .with_scale(size.extend(1.0)),
+            ));
+        }
+
+        if let Some(material) = &style.decal {
+            let spin = Quat::from_rotation_y(rng.gen_range(0.0..std::f32::consts::TAU));
+            let transform = Transform::from_translation(impact.point)
+                .with_rotation(Quat::from_rotation_arc(Vec3::Y, impact.normal) * spin)
+                .with_scale(Vec3::splat(style.decal_size));
+            spawn_decal(
+                &mut commands,
+                &mut live_decals,
+                effects.max_decals,
+                material.clone(),
+                transform,
+                impact.entity,
+                &bodies,
+            );
+        }
+
+        let plays = rng.gen_bool(style.sound_chance.clamp(0.0, 1.0) as f64);
+        if let Some(sound) = style.sounds.choose(&mut rng).filter(|_| plays) {
+            commands.spawn((
+                AudioPlayer::new(asset_server.load::<AudioSource>(sound)),
+                PlaybackSettings::DESPAWN,
+            ));
+        }
+    }
+}
+
+/// System: step puffs through their frames and remove them at the end
+fn animate_puffs(
+    mut commands: Commands,
+    time: Res<Time>,
+    effects: Res<ImpactEffects>,
+    mut puffs: Query<(
+        Entity,
+        &mut ImpactPuff,
+        &mut MeshMaterial3d<StandardMaterial>,
+        &mut Transform,
+    )>,
+) {
+    for (entity, mut puff, mut material, mut transform) in &mut puffs {
+        puff.timer -= time.delta_secs();
+        if puff.timer > 0.0 {
+            continue;
+        }
+        puff.frame += 1;
+        let frame = effects
+            .styles
+            .get(&puff.surface)
+            .and_then(|style| style.puff.get(puff.frame));
+        let Some((next, size, seconds)) = frame else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        puff.timer += seconds;
+        material.0 = next.clone();
+        transform.scale = size.extend(1.0);
+    }
+}
@@ -0,0 +1,139 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+
+use crate::{ControllerSuspended, GoldenControllerInput, LogicalPlayer, RenderPlayer};
+
+pub struct InteractPlugin;
+
+impl Plugin for InteractPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Interacted>().add_systems(
+            Update,
+            (update_interaction_focus, send_interactions, draw_focus).chain(),
+        );
+    }
+}
+
+/// Nothing further than this is ever focused, whatever the interactable's range
+const MAX_INTERACT_DISTANCE: f32 = 5.0;
+
+/// Something the player can use by looking at it and pressing the use key
+#[derive(Component)]
+pub struct Interactable {
+    /// Shown on the HUD while focused, like "Ride motorcycle"
+    pub prompt: String,
+    pub range: f32,
+}
+
+impl Interactable {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            range: 2.5,
+        }
+    }
+}
+
+/// The interactable a logical player is currently looking at
+#[derive(Component, Default)]
+pub struct InteractionFocus {
+    pub target: Option<Entity>,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Interacted {
+    pub player: Entity,
+    pub target: Entity,
+}
+
+fn update_interaction_focus(
+    spatial_query_pipeline: Res<SpatialQueryPipeline>,
+    cameras: Query<(&RenderPlayer, &GlobalTransform)>,
+    mut players: Query<(&mut InteractionFocus, Has<ControllerSuspended>), With<LogicalPlayer>>,
+    interactables: Query<&Interactable>,
+    collider_bodies: Query<&ColliderOf>,
+) {
+    for (render_player, camera_transform) in &cameras {
+        let Ok((mut focus, suspended)) = players.get_mut(render_player.logical_entity) else {
+            continue;
+        };
+        // riding a vehicle and the like, nothing to focus
+        if suspended {
+            focus.target = None;
+            continue;
+        }
+        let filter =
+            SpatialQueryFilter::default().with_excluded_entities([render_player.logical_entity]);
+        let hit = spatial_query_pipeline.cast_ray(
+            camera_transform.translation(),
+            camera_transform.forward(),
+            MAX_INTERACT_DISTANCE,
+            true,
+            &filter,
+        );
+
+        // the interactable can sit on the collider or on the rigid body that owns it
+        let target = hit.and_then(|hit| {
+            [
+                Some(hit.entity),
+                collider_bodies.get(hit.entity).ok().map(|of| of.body),
+            ]
+            .into_iter()
+            .flatten()
+            .find(|entity| {
+                interactables
+                    .get(*entity)
+                    .is_ok_and(|interactable| hit.distance <= interactable.range)
+            })
+        });
+        if focus.target != target {
+            focus.target = target;
+        }
+    }
+}
+
+fn send_interactions(
+    mut interactions: EventWriter<Interacted>,
+    players: Query<
+        (Entity, &GoldenControllerInput, &InteractionFocus),
+        Without<ControllerSuspended>,
+    >,
+) {
+    for (player, input, focus) in &players {
+        let Some(target) = focus.target.filter(|_| input.interact) else {
+            continue;
+        };
+        interactions.write(Interacted { player, target });
+    }
+}
+
+/// Outlines whatever is focused
+fn draw_focus(
+    mut gizmos: Gizmos,
+    players: Query<&InteractionFocus>,
+    targets: Query<(&GlobalTransform, Option<&Aabb>)>,
+) {
+    for focus in &players {
+        let Some((transform, aabb)) = focus.target.and_then(|target| targets.get(target).ok())
+        else {
+            continue;
+        };
+        match aabb {
+            Some(aabb) => {
+                let center = transform.transform_point(aabb.center.into());
+                let size =
+                    transform.compute_transform().scale * Vec3::from(aabb.half_extents) * 2.0;
+                gizmos.cuboid(
+                    Transform::from_translation(center)
+                        .with_rotation(transform.rotation())
+                        .with_scale(size * 1.05),
+                    Color::WHITE,
+                );
+            }
+            None => {
+                gizmos.sphere(transform.translation(), 0.5, Color::WHITE);
+            }
+        }
+    }
+}
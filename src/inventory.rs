@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
+
+use crate::{Armor, Carrier, Damageable, GrenadeThrow, Health, InteractionFocus};
 pub struct InventoryPlugin;
 
 impl Plugin for InventoryPlugin {
@@ -19,16 +23,51 @@ fn keyboard_input(keyboard_input: Res<ButtonInput<KeyCode>>) {
 pub struct PlayerStuffBundle {
     pub inventory: PlayerInventory,
     pub stats: PlayerStats,
+    pub health: Health,
+    pub armor: Armor,
+    pub damageable: Damageable,
+    pub focus: InteractionFocus,
+    pub carrier: Carrier,
+    pub grenade_throw: GrenadeThrow,
 }
 
 #[derive(Default, Component)]
 pub struct PlayerInventory {
     pub bandages: u16,
     pub armor_bits: u16,
-    pub grenades: u16,
-    pub ninemm_ammo: u16,
+    /// DECORATE style items by lowercase name, like a weapon's loaded rounds and reserve ammo.
+    /// The throw bind's grenades are [`crate::GRENADE_AMMO`], see [`GrenadeThrow`]
+    pub items: HashMap<String, u32>,
+}
+
+impl PlayerInventory {
+    pub fn count(&self, item: &str) -> u32 {
+        self.items.get(&item.to_lowercase()).copied().unwrap_or(0)
+    }
+
+    /// Reserve 9mm rounds, the `NineMilAmmo` item the pistols share
+    pub fn ninemm_ammo(&self) -> u32 {
+        self.count("ninemilammo")
+    }
+
+    /// Adds up to `limit`, returns how many were added
+    pub fn give(&mut self, item: &str, amount: u32, limit: Option<u32>) -> u32 {
+        let count = self.items.entry(item.to_lowercase()).or_default();
+        let given = amount.min(limit.unwrap_or(u32::MAX).saturating_sub(*count));
+        *count += given;
+        given
+    }
+
+    /// Removes up to `amount`, returns how many were removed
+    pub fn take(&mut self, item: &str, amount: u32) -> u32 {
+        let count = self.items.entry(item.to_lowercase()).or_default();
+        let taken = amount.min(*count);
+        *count -= taken;
+        taken
+    }
 }
 
+/// Whole number copy of a player's [`Health`] and [`Armor`], kept up to date by the damage plugin
 #[derive(Component)]
 pub struct PlayerStats {
     pub health: i16,
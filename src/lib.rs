@@ -12,3 +12,47 @@ mod ui;
 pub use ui::*;
 mod inventory;
 pub use inventory::*;
+mod triggers;
+pub use triggers::*;
+mod respawn;
+pub use respawn::*;
+mod split_screen;
+pub use split_screen::*;
+mod gravity;
+pub use gravity::*;
+mod vehicle;
+pub use vehicle::*;
+mod interact;
+pub use interact::*;
+mod carry;
+pub use carry::*;
+mod weapon;
+pub use weapon::*;
+mod decorate;
+pub use decorate::*;
+mod weapon_state;
+pub use weapon_state::*;
+mod reload;
+pub use reload::*;
+mod weapon_slots;
+pub use weapon_slots::*;
+mod recoil;
+pub use recoil::*;
+mod damage;
+pub use damage::*;
+mod impacts;
+pub use impacts::*;
+mod projectile;
+pub use projectile::*;
+mod ballistics;
+pub use ballistics::*;
+mod melee;
+pub use melee::*;
+mod explosives;
+pub use explosives::*;
+mod aim;
+pub use aim::*;
+mod upgrades;
+pub use upgrades::*;
+mod lights;
+pub use lights::*;
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+use bevy::ecs::entity::Entities;
+use bevy::prelude::*;
+
+use crate::{DecorateWeapons, MAP_UNITS_PER_METER, ScriptCursor};
+
+/// Dynamic lights from GLDEFS: point and pulse light presets spawned as Bevy [`PointLight`]s,
+/// either left in the world for a moment like a muzzle flash or following something like a bolt
+pub struct GlLightPlugin;
+
+impl Plugin for GlLightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightPresets>()
+            .add_event::<SpawnLight>()
+            .add_systems(
+                Startup,
+                add_gldefs_lights.run_if(resource_exists::<DecorateWeapons>),
+            )
+            .add_systems(Update, (spawn_lights, animate_lights).chain());
+    }
+}
+
+/// The built in light [`WeaponDef::muzzle_light`](crate::WeaponDef::muzzle_light) defaults to
+pub const MUZZLE_FLASH_LIGHT: &str = "MuzzleFlash";
+
+/// Lights every game gets, the pack's GLDEFS replaces any it defines with the same name
+const BUILTIN_GLDEFS: &str = "
+pointlight MuzzleFlash
+{
+    color 1.0 0.8 0.5
+    size 96
+}
+pulselight ProjectileGlow
+{
+    color 1.0 0.7 0.4
+    size 40
+    secondarysize 32
+    interval 0.2
+}
+";
+
+/// Lumens per square metre of range, so bigger lights are brighter the way they are in GZDoom
+const LUMENS_PER_SQUARE_METRE: f32 = 20_000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LightKind {
+    Point,
+    /// Swings between `size` and `secondary_size` once every `interval` seconds
+    Pulse {
+        secondary_size: f32,
+        interval: f32,
+    },
+}
+
+/// One GLDEFS light definition, sizes and offsets still in map units
+#[derive(Clone, Debug)]
+pub struct LightDef {
+    pub kind: LightKind,
+    pub color: Color,
+    /// Radius in map units
+    pub size: f32,
+    /// GLDEFS `offset X Y Z`, X forward, Y up and Z to the left
+    pub offset: Vec3,
+    /// Darkens instead of lights, which Bevy can't do, so these stay off
+    pub subtractive: bool,
+}
+
+impl Default for LightDef {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            color: Color::WHITE,
+            size: 64.0,
+            offset: Vec3::ZERO,
+            subtractive: false,
+        }
+    }
+}
+
+impl LightDef {
+    /// Radius in map units `age` seconds after it was spawned
+    pub fn size_at(&self, age: f32) -> f32 {
+        match self.kind {
+            LightKind::Point => self.size,
+            LightKind::Pulse {
+                secondary_size,
+                interval,
+            } => {
+                let phase = 0.5 - 0.5 * (TAU * age / interval.max(f32::EPSILON)).cos();
+                self.size + (secondary_size - self.size) * phase
+            }
+        }
+    }
+
+    /// The offset in metres, with Bevy's forward being -Z
+    pub fn offset_metres(&self) -> Vec3 {
+        Vec3::new(-self.offset.z, self.offset.y, -self.offset.x) / MAP_UNITS_PER_METER
+    }
+
+    /// A Bevy light for the given radius in map units
+    pub fn point_light(&self, size: f32) -> PointLight {
+        let range = size / MAP_UNITS_PER_METER;
+        PointLight {
+            color: self.color,
+            intensity: if self.subtractive {
+                0.0
+            } else {
+                LUMENS_PER_SQUARE_METRE * range * range
+            },
+            range,
+            shadows_enabled: false,
+            ..default()
+        }
+    }
+}
+
+/// Every light from GLDEFS by lowercase name. Flicker and sector lights are read as point lights
+/// at their primary size, `object` and `brightmap` blocks are skipped.
+pub fn parse_gldefs(source: &str) -> HashMap<String, LightDef> {
+    let mut lights = HashMap::new();
+    let mut cursor = ScriptCursor::new(source);
+    loop {
+        cursor.skip_lines();
+        if cursor.peek().is_none() {
+            break;
+        }
+        let keyword = cursor.token().to_lowercase();
+        match keyword.as_str() {
+            "pointlight" | "pulselight" | "flickerlight" | "flickerlight2" | "sectorlight" => {}
+            // no braces to skip
+            "#include" => {
+                cursor.rest_of_line();
+                continue;
+            }
+            "" => {
+                // stray punctuation
+                cursor.bump();
+                continue;
+            }
+            _ => {
+                cursor.skip_statement();
+                continue;
+            }
+        }
+        let name = cursor.token().to_lowercase();
+        cursor.skip_lines();
+        if !cursor.eat(b'{') {
+            cursor.rest_of_line();
+            continue;
+        }
+
+        let mut def = LightDef::default();
+        let mut secondary_size = None;
+        let mut interval = 1.0;
+        loop {
+            cursor.skip_lines();
+            if cursor.peek().is_none() || cursor.eat(b'}') {
+                break;
+            }
+            let property = cursor.token().to_lowercase();
+            let mut number = || cursor.token().parse::<f32>().unwrap_or(0.0);
+            match property.as_str() {
+                "color" => def.color = Color::srgb(number(), number(), number()),
+                "size" => def.size = number(),
+                "secondarysize" => secondary_size = Some(number()),
+                "interval" => interval = number(),
+                "offset" => def.offset = Vec3::new(number(), number(), number()),
+                "subtractive" => def.subtractive = number() != 0.0,
+                _ => {}
+            }
+            cursor.rest_of_line();
+        }
+        if keyword == "pulselight" {
+            def.kind = LightKind::Pulse {
+                secondary_size: secondary_size.unwrap_or(def.size),
+                interval,
+            };
+        }
+        lights.insert(name, def);
+    }
+    lights
+}
+
+/// Light presets by lowercase GLDEFS name
+#[derive(Resource)]
+pub struct LightPresets(pub HashMap<String, LightDef>);
+
+impl Default for LightPresets {
+    fn default() -> Self {
+        Self(parse_gldefs(BUILTIN_GLDEFS))
+    }
+}
+
+impl LightPresets {
+    pub fn get(&self, name: &str) -> Option<&LightDef> {
+        self.0.get(&name.to_lowercase())
+    }
+}
+
+/// Lights up a [`LightPresets`] light on the next update
+#[derive(Event, Clone, Debug)]
+pub struct SpawnLight {
+    /// GLDEFS light name
+    pub preset: String,
+    /// What the light follows, `None` leaves it where it was put
+    pub parent: Option<Entity>,
+    /// Relative to the parent, or in the world without one, the preset's offset is added on top
+    pub position: Vec3,
+    /// Seconds it fades out over, `None` keeps it until its parent goes
+    pub lifetime: Option<f32>,
+}
+
+impl SpawnLight {
+    /// A light that fades out over `lifetime` seconds, like a muzzle flash
+    pub fn flash(preset: &str, parent: Entity, position: Vec3, lifetime: f32) -> Self {
+        Self {
+            preset: preset.to_string(),
+            parent: Some(parent),
+            position,
+            lifetime: Some(lifetime),
+        }
+    }
+
+    /// A light that stays on `parent` for as long as it's around
+    pub fn attached(preset: &str, parent: Entity) -> Self {
+        Self {
+            preset: preset.to_string(),
+            parent: Some(parent),
+            position: Vec3::ZERO,
+            lifetime: None,
+        }
+    }
+}
+
+/// A light spawned from a preset, animated by its kind
+#[derive(Component)]
+pub struct GlLight {
+    pub def: LightDef,
+    /// Seconds since it was spawned
+    pub age: f32,
+    pub lifetime: Option<f32>,
+}
+
+/// System: the pack's GLDEFS lights, on top of the built in ones
+fn add_gldefs_lights(decorate: Res<DecorateWeapons>, mut presets: ResMut<LightPresets>) {
+    presets.0.extend(
+        decorate
+            .lights
+            .iter()
+            .map(|(name, def)| (name.clone(), def.clone())),
+    );
+}
+
+/// System: turn spawn requests into lights, on their parent if it's still around
+fn spawn_lights(
+    mut commands: Commands,
+    entities: &Entities,
+    presets: Res<LightPresets>,
+    mut requests: EventReader<SpawnLight>,
+) {
+    for request in requests.read() {
+        let Some(def) = presets.get(&request.preset) else {
+            warn_once!("no light preset called {}", request.preset);
+            continue;
+        };
+        if request
+            .parent
+            .is_some_and(|parent| !entities.contains(parent))
+        {
+            continue;
+        }
+        let mut light = commands.spawn((
+            def.point_light(def.size),
+            Transform::from_translation(request.position + def.offset_metres()),
+            GlLight {
+                def: def.clone(),
+                age: 0.0,
+                lifetime: request.lifetime,
+            },
+        ));
+        if let Some(parent) = request.parent {
+            light.insert(ChildOf(parent));
+        }
+    }
+}
+
+/// System: pulse lights by their interval and fade out short lived ones
+fn animate_lights(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut lights: Query<(Entity, &mut GlLight, &mut PointLight)>,
+) {
+    for (entity, mut gl_light, mut light) in &mut lights {
+        gl_light.age += time.delta_secs();
+        let fade = match gl_light.lifetime {
+            Some(lifetime) if gl_light.age >= lifetime => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+            Some(lifetime) => 1.0 - gl_light.age / lifetime,
+            None => 1.0,
+        };
+        let size = gl_light.def.size_at(gl_light.age);
+        let animated = gl_light.def.point_light(size);
+        light.range = animated.range;
+        light.intensity = animated.intensity * fade;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_and_pulse_lights() {
+        let lights = parse_gldefs(
+            "pointlight MuzzleFlash
+            {
+                color 1.0 0.8 0.2
+                size 48
+                offset 0 40 0
+            }
+            PulseLight ProjectileGlow
+            {
+                color 0.2 0.4 1.0
+                size 24
+                secondarysize 32
+                interval 0.5
+            }",
+        );
+        let flash = &lights["muzzleflash"];
+        assert_eq!(flash.kind, LightKind::Point);
+        assert_eq!(flash.color, Color::srgb(1.0, 0.8, 0.2));
+        assert_eq!(flash.size, 48.0);
+        assert_eq!(flash.offset, Vec3::new(0.0, 40.0, 0.0));
+        assert_eq!(
+            lights["projectileglow"].kind,
+            LightKind::Pulse {
+                secondary_size: 32.0,
+                interval: 0.5
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_gldefs_are_skipped() {
+        let lights = parse_gldefs(
+            "#include \"more.txt\"
+            object Imp { frame TROO { light IMPBALL } }
+            , ;
+            pointlight Lonely
+            size 8
+            pointlight Fine
+            {
+                ;
+                size 16
+                subtractive 1
+            }
+            flickerlight Open
+            {
+                size 20",
+        );
+        assert_eq!(lights.len(), 2);
+        assert!(!lights.contains_key("lonely"));
+        assert_eq!(lights["fine"].size, 16.0);
+        assert!(lights["fine"].subtractive);
+        assert_eq!(lights["open"].size, 20.0);
+    }
+
+    #[test]
+    fn builtin_muzzle_flash_parses() {
+        let presets = LightPresets::default();
+        assert!(presets.get(MUZZLE_FLASH_LIGHT).is_some());
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+
+use avian3d::prelude::*;
+use bevy::pbr::decal::{ForwardDecalMaterial, ForwardDecalMaterialExt};
+use bevy::prelude::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::{
+    DT, DamageEvent, DamageType, Damageable, DecorateWeapons, GoldenControllerInput, ImpactEffects,
+    LiveDecals, MAP_UNITS_PER_METER, RenderPlayer, SurfaceMaterial, TICS_PER_SECOND, WEAPON_TOP,
+    Weapon, WeaponDef, WeaponStateAction, WeaponStateMachine, spawn_decal, step_weapon_states,
+};
+
+/// Melee swings: `A_CustomPunch` from weapon states and a quick melee for every other weapon,
+/// swept over a few tics with shape casts
+pub struct MeleePlugin;
+
+impl Plugin for MeleePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .init_resource::<MeleeEffects>()
+            // wall marks count against the same decal budget as bullet holes
+            .init_resource::<ImpactEffects>()
+            .init_resource::<LiveDecals>()
+            .add_systems(
+                Startup,
+                add_puff_sounds.run_if(resource_exists::<DecorateWeapons>),
+            )
+            .add_systems(
+                FixedUpdate,
+                (start_punches, quick_melee, swing_melee)
+                    .chain()
+                    .after(step_weapon_states),
+            );
+    }
+}
+
+/// Degrees a swing sweeps across, right to left
+const SWING_ARC: f32 = 70.0;
+
+/// Tics a swing takes to sweep its arc
+const SWING_TICS: f32 = 4.0;
+
+/// Metres, how thick the swept blade or bar is
+const SWING_RADIUS: f32 = 0.1;
+
+/// Seconds the weapon's states freeze when a swing connects
+const HIT_STOP: f32 = 0.08;
+
+/// Seconds after a quick melee before the gun can fire or swing again
+const QUICK_MELEE_RECOVERY: f32 = 0.4;
+
+/// Map units, zdoom's default `A_CustomPunch` range
+pub const MELEE_RANGE: f32 = 64.0;
+
+/// Puff used when a punch names one without a style
+const DEFAULT_STYLE: &str = "crowbarpuff";
+
+/// How swings with one puff sound and mark walls
+#[derive(Clone, Default)]
+pub struct MeleeStyle {
+    /// Asset paths for a swing that hit nothing, one is picked at random
+    pub miss: Vec<String>,
+    pub wall: Vec<String>,
+    pub flesh: Vec<String>,
+    /// Left on walls, `None` leaves no mark
+    pub decal: Option<Handle<ForwardDecalMaterial<StandardMaterial>>>,
+    /// Metres along and across the swing
+    pub decal_size: Vec2,
+}
+
+/// Melee styles by lowercase puff actor, like the `A_CustomPunch` puff argument
+#[derive(Resource)]
+pub struct MeleeEffects {
+    pub styles: HashMap<String, MeleeStyle>,
+}
+
+impl FromWorld for MeleeEffects {
+    fn from_world(world: &mut World) -> Self {
+        let scrape = world
+            .resource::<AssetServer>()
+            .load("AshesWeaponsV357/Graphics/KNWALG.png");
+        let knife_scrape = world
+            .resource_mut::<Assets<ForwardDecalMaterial<StandardMaterial>>>()
+            .add(ForwardDecalMaterial {
+                base: StandardMaterial {
+                    base_color_texture: Some(scrape),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                },
+                extension: ForwardDecalMaterialExt {
+                    depth_fade_factor: 1.0,
+                },
+            });
+
+        // the sounds come from the pack's puffs, see `add_puff_sounds`
+        let styles = HashMap::from([(
+            "knifepuff".to_string(),
+            MeleeStyle {
+                // DECALDEF's Knifescrape, KNWALG at 0.8 scale
+                decal: Some(knife_scrape),
+                decal_size: Vec2::new(16.0, 8.0) * 0.8 / MAP_UNITS_PER_METER,
+                ..default()
+            },
+        )]);
+        Self { styles }
+    }
+}
+
+/// System: the sounds of the puffs the pack's punches name, looked up through SNDINFO
+fn add_puff_sounds(decorate: Res<DecorateWeapons>, mut effects: ResMut<MeleeEffects>) {
+    for (name, sounds) in &decorate.puffs {
+        let style = effects.styles.entry(name.clone()).or_default();
+        style.miss = sounds.miss.clone();
+        style.wall = sounds.wall.clone();
+        style.flesh = sounds.flesh.clone();
+    }
+}
+
+/// A swing in progress on a logical player, ends at the first thing it hits
+#[derive(Component)]
+pub struct MeleeSwing {
+    /// Lowercase puff actor, picks the [`MeleeStyle`]
+    pub style: String,
+    pub damage: f32,
+    /// Metres
+    pub range: f32,
+    /// Seconds the sweep takes
+    pub duration: f32,
+    /// Seconds after the sweep the swing stays around, blocking another quick melee
+    pub recovery: f32,
+    elapsed: f32,
+    /// Hit something or swept the whole arc
+    done: bool,
+}
+
+impl MeleeSwing {
+    pub fn new(style: &str, damage: f32, range: f32) -> Self {
+        Self {
+            style: style.to_lowercase(),
+            damage,
+            range,
+            duration: SWING_TICS / TICS_PER_SECOND,
+            recovery: 0.0,
+            elapsed: 0.0,
+            done: false,
+        }
+    }
+}
+
+/// `A_CustomPunch` damage, a number or the pack's `random(min, max) * factor`,
+/// `roll` picks a number in each range
+pub(crate) fn punch_damage(arg: &str, mut roll: impl FnMut(i32, i32) -> f32) -> Option<f32> {
+    arg.split('*')
+        .map(|factor| {
+            let factor = factor.trim().to_lowercase();
+            match factor
+                .strip_prefix("random(")
+                .and_then(|range| range.strip_suffix(')'))
+            {
+                Some(range) => {
+                    let (min, max) = range.split_once(',')?;
+                    let min = min.trim().parse::<i32>().ok()?;
+                    let max = max.trim().parse::<i32>().ok()?;
+                    Some(roll(min.min(max), max.max(min)))
+                }
+                None => factor.parse().ok(),
+            }
+        })
+        .product()
+}
+
+/// System: `A_CustomPunch(damage, norandom, flags, puff, range)` starts a swing
+fn start_punches(mut commands: Commands, mut state_actions: EventReader<WeaponStateAction>) {
+    let mut rng = rand::thread_rng();
+    for WeaponStateAction { player, action } in state_actions.read() {
+        if action.name != "a_custompunch" {
+            continue;
+        }
+        let roll = |min: i32, max: i32| rng.gen_range(min..=max) as f32;
+        let Some(mut damage) = action.arg(0).and_then(|arg| punch_damage(arg, roll)) else {
+            continue;
+        };
+        // like doom's fist, damage is multiplied by 1 to 8 unless told not to
+        let norandom = action
+            .arg(1)
+            .is_some_and(|arg| matches!(arg.to_lowercase().as_str(), "1" | "true"));
+        if !norandom {
+            damage *= rng.gen_range(1..=8) as f32;
+        }
+        let puff = action.arg_str(3).unwrap_or(DEFAULT_STYLE);
+        let range = action.arg_f32(4).unwrap_or(MELEE_RANGE) / MAP_UNITS_PER_METER;
+        commands
+            .entity(*player)
+            .insert(MeleeSwing::new(puff, damage, range));
+    }
+}
+
+/// System: the quick melee bind swings the pack's bootknife,
+/// for weapons without a `User1` state of their own
+fn quick_melee(
+    mut commands: Commands,
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut players: Query<
+        (
+            Entity,
+            &GoldenControllerInput,
+            &mut Weapon,
+            &WeaponStateMachine,
+        ),
+        Without<MeleeSwing>,
+    >,
+) {
+    let mut rng = rand::thread_rng();
+    for (player, input, mut weapon, machine) in &mut players {
+        if !input.melee || weapon.reload.is_some() || machine.y > WEAPON_TOP {
+            continue;
+        }
+        let Some(def) = weapon_defs.get(&weapon.def) else {
+            continue;
+        };
+        let has_user1 = def
+            .states
+            .as_ref()
+            .is_some_and(|states| states.label("user1").is_some());
+        if has_user1 {
+            continue;
+        }
+        // the pack's A_CustomPunch(random(5, 10) * 5, TRUE, 0, "knifePuff", 80)
+        let damage = rng.gen_range(5..=10) as f32 * 5.0;
+        let swing = MeleeSwing {
+            recovery: QUICK_MELEE_RECOVERY,
+            ..MeleeSwing::new("knifepuff", damage, 80.0 / MAP_UNITS_PER_METER)
+        };
+        weapon.cooldown = weapon.cooldown.max(swing.duration + swing.recovery);
+        commands.entity(player).insert(swing);
+    }
+}
+
+/// System: sweep each swing across its arc from the player's camera,
+/// hurting, sounding and marking whatever it runs into first
+#[allow(clippy::too_many_arguments)]
+fn swing_melee(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    effects: Res<MeleeEffects>,
+    impact_effects: Res<ImpactEffects>,
+    mut live_decals: ResMut<LiveDecals>,
+    spatial_query: SpatialQuery,
+    mut damage: EventWriter<DamageEvent>,
+    cameras: Query<(&GlobalTransform, &RenderPlayer), With<Camera3d>>,
+    mut swings: Query<(&mut MeleeSwing, Option<&mut WeaponStateMachine>)>,
+    surfaces: Query<&SurfaceMaterial>,
+    damageables: Query<(), With<Damageable>>,
+    sensors: Query<(), With<Sensor>>,
+    bodies: Query<(&RigidBody, &GlobalTransform)>,
+) {
+    let mut rng = rand::thread_rng();
+    let blade = Collider::sphere(SWING_RADIUS);
+    for (global, render_player) in &cameras {
+        let player = render_player.logical_entity;
+        let Ok((mut swing, machine)) = swings.get_mut(player) else {
+            continue;
+        };
+        swing.elapsed += DT;
+        if swing.done {
+            if swing.elapsed >= swing.duration + swing.recovery {
+                commands.entity(player).remove::<MeleeSwing>();
+            }
+            continue;
+        }
+        let Some(style) = effects
+            .styles
+            .get(&swing.style)
+            .or_else(|| effects.styles.get(DEFAULT_STYLE))
+        else {
+            continue;
+        };
+
+        let progress = (swing.elapsed / swing.duration).min(1.0);
+        let sweep = Quat::from_rotation_y(SWING_ARC.to_radians() * (progress - 0.5));
+        let rotation = global.rotation() * sweep;
+        let direction = Dir3::new_unchecked(rotation * Vec3::NEG_Z);
+        let filter = SpatialQueryFilter::default().with_excluded_entities([player]);
+        // triggers aren't in the way
+        let solid = |entity: Entity| !sensors.contains(entity);
+        let hit = spatial_query.cast_shape_predicate(
+            &blade,
+            global.translation(),
+            Quat::IDENTITY,
+            direction,
+            &ShapeCastConfig::from_max_distance(swing.range),
+            &filter,
+            &solid,
+        );
+        let Some(hit) = hit else {
+            if progress >= 1.0 {
+                swing.done = true;
+                play(&mut commands, &asset_server, &style.miss, &mut rng);
+            }
+            continue;
+        };
+        swing.done = true;
+        if let Some(mut machine) = machine {
+            machine.hit_stop = HIT_STOP;
+        }
+
+        let surface = SurfaceMaterial::of(hit.entity, &surfaces, &damageables);
+        if damageables.contains(hit.entity) {
+            damage.write(DamageEvent {
+                target: hit.entity,
+                amount: swing.damage,
+                damage_type: DamageType::Melee,
+                source: Some(player),
+                hit_point: Some(hit.point1),
+            });
+        }
+        if surface == SurfaceMaterial::Flesh {
+            play(&mut commands, &asset_server, &style.flesh, &mut rng);
+            continue;
+        }
+        play(&mut commands, &asset_server, &style.wall, &mut rng);
+
+        if let Some(material) = &style.decal {
+            // the scrape runs the way the swing was going
+            let normal = hit.normal1.normalize_or(-direction.as_vec3());
+            let along = rotation * Vec3::NEG_X;
+            let along = (along - normal * along.dot(normal))
+                .try_normalize()
+                .unwrap_or_else(|| normal.any_orthonormal_vector());
+            let transform = Transform::from_translation(hit.point1)
+                .with_rotation(Quat::from_mat3(&Mat3::from_cols(
+                    along,
+                    normal,
+                    along.cross(normal),
+                )))
+                .with_scale(Vec3::new(
+                    style.decal_size.x,
+                    style.decal_size.y,
+                    style.decal_size.y,
+                ));
+            spawn_decal(
+                &mut commands,
+                &mut live_decals,
+                impact_effects.max_decals,
+                material.clone(),
+                transform,
+                hit.entity,
+                &bodies,
+            );
+        }
+    }
+}
+
+fn play(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    sounds: &[String],
+    rng: &mut impl Rng,
+) {
+    if let Some(sound) = sounds.choose(rng) {
+        commands.spawn((
+            AudioPlayer::new(asset_server.load::<AudioSource>(sound)),
+            PlaybackSettings::DESPAWN,
+        ));
+    }
+}
@@ -0,0 +1,325 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{DT, DamageType, ShotHit, SpawnLight, shoot_bullet};
+
+/// Pooled projectile bullets: one shared mesh and material, bounded lifetime, range and count
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LaunchProjectile>()
+            .add_event::<ShotHit>()
+            .add_event::<SpawnLight>()
+            .init_resource::<ProjectileSettings>()
+            .init_resource::<ProjectileAssets>()
+            .add_systems(
+                FixedUpdate,
+                (projectile_impacts, age_projectiles, launch_projectiles)
+                    .chain()
+                    .after(shoot_bullet),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct ProjectileSettings {
+    /// Seconds a bullet flies before going back to the pool
+    pub max_lifetime: f32,
+    /// Metres from where it was fired
+    pub max_distance: f32,
+    /// Bullets slower than this in m/s are spent, so ones that got stuck don't stay around
+    pub min_speed: f32,
+    /// Bullets alive or pooled, past this the oldest one in flight is reused,
+    /// and launches past it within one tick are dropped
+    pub max_live: usize,
+    /// Spend bullets on their first hit instead of letting them bounce
+    pub despawn_on_impact: bool,
+}
+
+impl Default for ProjectileSettings {
+    fn default() -> Self {
+        Self {
+            max_lifetime: 5.0,
+            max_distance: 500.0,
+            min_speed: 50.0,
+            max_live: 256,
+            despawn_on_impact: true,
+        }
+    }
+}
+
+/// Mesh and material every bullet shares
+#[derive(Resource)]
+pub struct ProjectileAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ProjectileAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(0.01));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::srgb(0.8, 0.7, 0.6),
+                emissive: LinearRgba::new(0.4, 0.3, 0.2, 0.1),
+                perceptual_roughness: 0.1,
+                metallic: 0.8,
+                ..default()
+            });
+        Self { mesh, material }
+    }
+}
+
+/// Fires a pooled bullet on the next fixed tick
+#[derive(Event, Clone, Debug)]
+pub struct LaunchProjectile {
+    /// The logical player who fired
+    pub shooter: Entity,
+    pub origin: Vec3,
+    pub direction: Dir3,
+    /// m/s
+    pub speed: f32,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    /// See [`ShotHit::shot`]
+    pub shot: u32,
+    /// GLDEFS light the bullet carries
+    pub light: Option<String>,
+}
+
+#[derive(Component)]
+#[require(CollisionEventsEnabled)]
+pub struct Bullet {
+    pub shooter: Entity,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub shot: u32,
+    /// Where it was fired from, for the range limit
+    pub origin: Vec3,
+    /// Seconds in flight, `None` while waiting in the pool
+    pub age: Option<f32>,
+    /// Position and velocity before the last physics step, to find what it hit
+    last: (Vec3, Vec3),
+}
+
+impl Bullet {
+    fn in_flight(&self) -> bool {
+        self.age.is_some()
+    }
+}
+
+/// Parks a bullet in the pool, hidden and out of the physics world
+fn retire(commands: &mut Commands, entity: Entity, bullet: &mut Bullet) {
+    bullet.age = None;
+    commands.entity(entity).insert((
+        RigidBodyDisabled,
+        ColliderDisabled,
+        Visibility::Hidden,
+        LinearVelocity::ZERO,
+    ));
+}
+
+/// System: fire launches from the pool, spawning only while under the cap
+fn launch_projectiles(
+    mut commands: Commands,
+    settings: Res<ProjectileSettings>,
+    assets: Res<ProjectileAssets>,
+    mut launches: EventReader<LaunchProjectile>,
+    mut lights: EventWriter<SpawnLight>,
+    mut bullets: Query<(Entity, &mut Bullet)>,
+) {
+    if launches.is_empty() {
+        return;
+    }
+    let mut pooled = Vec::new();
+    let mut flying = Vec::new();
+    for (entity, bullet) in &bullets {
+        match bullet.age {
+            None => pooled.push(entity),
+            Some(age) => flying.push((entity, age)),
+        }
+    }
+    let mut total = pooled.len() + flying.len();
+    // youngest first, so popping takes the oldest
+    flying.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    for launch in launches.read() {
+        let position = launch.origin + launch.direction * 1.0;
+        let velocity = launch.direction * launch.speed;
+        let bullet = Bullet {
+            shooter: launch.shooter,
+            damage: launch.damage,
+            damage_type: launch.damage_type,
+            shot: launch.shot,
+            origin: launch.origin,
+            age: Some(0.0),
+            last: (position, velocity),
+        };
+        // at the cap the oldest bullet still in flight is fired again
+        let reused = pooled.pop().or_else(|| {
+            if total >= settings.max_live {
+                flying.pop().map(|(entity, _)| entity)
+            } else {
+                None
+            }
+        });
+        let entity = match reused {
+            // the whole cap went out this tick already, there's nothing older to take back
+            None if total >= settings.max_live => continue,
+            Some(entity) => {
+                if let Ok((_, mut pooled_bullet)) = bullets.get_mut(entity) {
+                    *pooled_bullet = bullet;
+                }
+                commands
+                    .entity(entity)
+                    .remove::<(RigidBodyDisabled, ColliderDisabled)>()
+                    // a light from its last flight goes with it
+                    .despawn_related::<Children>()
+                    .insert((
+                        Transform::from_translation(position),
+                        LinearVelocity(velocity),
+                        AngularVelocity::ZERO,
+                        Visibility::Inherited,
+                    ));
+                entity
+            }
+            None => {
+                total += 1;
+                commands
+                    .spawn((
+                        bullet,
+                        Collider::sphere(0.001),
+                        Mesh3d(assets.mesh.clone()),
+                        MeshMaterial3d(assets.material.clone()),
+                        RigidBody::Dynamic,
+                        Mass(0.001),
+                        SpeculativeMargin::ZERO,
+                        Transform::from_translation(position),
+                        LinearVelocity(velocity),
+                        GravityScale(1.0),
+                        SweptCcd::default(),
+                        Friction::new(0.1),
+                        Restitution::new(0.6),
+                        LinearDamping(0.01),
+                    ))
+                    .id()
+            }
+        };
+        if let Some(light) = &launch.light {
+            lights.write(SpawnLight::attached(light, entity));
+        }
+    }
+}
+
+/// System: spend bullets that flew too long, too far or came to rest
+fn age_projectiles(
+    mut commands: Commands,
+    settings: Res<ProjectileSettings>,
+    mut bullets: Query<(Entity, &mut Bullet, &Transform, &LinearVelocity)>,
+) {
+    for (entity, mut bullet, transform, velocity) in &mut bullets {
+        let Some(age) = bullet.age else {
+            continue;
+        };
+        let age = age + DT;
+        bullet.age = Some(age);
+        bullet.last = (transform.translation, velocity.0);
+        let spent = age > settings.max_lifetime
+            || transform.translation.distance(bullet.origin) > settings.max_distance
+            || (age > DT && velocity.length() < settings.min_speed);
+        if spent {
+            retire(&mut commands, entity, &mut bullet);
+        }
+    }
+}
+
+/// System: bullets that touched something hit it, and go back to the pool if set to
+pub(crate) fn projectile_impacts(
+    mut commands: Commands,
+    settings: Res<ProjectileSettings>,
+    spatial_query: SpatialQuery,
+    mut started: EventReader<CollisionStarted>,
+    mut shot_hits: EventWriter<ShotHit>,
+    mut bullets: Query<(&mut Bullet, &Transform)>,
+    sensors: Query<(), With<Sensor>>,
+) {
+    for CollisionStarted(a, b) in started.read() {
+        let (entity, other) = if bullets.contains(*a) {
+            (*a, *b)
+        } else {
+            (*b, *a)
+        };
+        let Ok((mut bullet, transform)) = bullets.get_mut(entity) else {
+            continue;
+        };
+        // triggers aren't in the way, and the bullet left the shooter's collider on its own
+        if !bullet.in_flight() || other == bullet.shooter || sensors.contains(other) {
+            continue;
+        }
+
+        // the step that ended in the hit, the bounce already turned the velocity around
+        let (start, velocity) = bullet.last;
+        let direction = Dir3::new(velocity).unwrap_or(Dir3::NEG_Z);
+        let filter = SpatialQueryFilter::default().with_excluded_entities([entity, bullet.shooter]);
+        let reach = velocity.length() * DT * 2.0 + 0.1;
+        let (point, normal) = spatial_query
+            .cast_ray(start, direction, reach, true, &filter)
+            .filter(|hit| hit.entity == other)
+            .map_or((transform.translation, -direction.as_vec3()), |hit| {
+                (start + direction * hit.distance, hit.normal)
+            });
+
+        shot_hits.write(ShotHit {
+            shooter: bullet.shooter,
+            entity: other,
+            point,
+            normal,
+            direction,
+            distance: point.distance(bullet.origin),
+            damage: bullet.damage,
+            damage_type: bullet.damage_type,
+            shot: bullet.shot,
+        });
+        if settings.despawn_on_impact {
+            retire(&mut commands, entity, &mut bullet);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_tick_of_launches_stays_under_the_cap() {
+        let mut app = App::new();
+        app.add_event::<LaunchProjectile>()
+            .add_event::<SpawnLight>()
+            .insert_resource(ProjectileSettings {
+                max_live: 4,
+                ..default()
+            })
+            .insert_resource(ProjectileAssets {
+                mesh: Handle::default(),
+                material: Handle::default(),
+            })
+            .add_systems(Update, launch_projectiles);
+        let launches = (0..10).map(|shot| LaunchProjectile {
+            shooter: Entity::PLACEHOLDER,
+            origin: Vec3::ZERO,
+            direction: Dir3::NEG_Z,
+            speed: 100.0,
+            damage: 10.0,
+            damage_type: DamageType::Normal,
+            shot,
+            light: None,
+        });
+        app.world_mut().send_event_batch(launches);
+        app.update();
+
+        let mut bullets = app.world_mut().query::<&Bullet>();
+        assert_eq!(bullets.iter(app.world()).count(), 4);
+    }
+}
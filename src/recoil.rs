@@ -0,0 +1,124 @@
+use std::f32::consts::FRAC_PI_2;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{
+    ANGLE_EPSILON, DT, GoldenControllerInput, GoldenControllerMutables,
+    GoldenControllerSpatialHits, Recoil, Weapon, WeaponDef, shoot_bullet,
+};
+
+/// Extra spread in degrees while off the ground
+const AIRBORNE_SPREAD: f32 = 3.0;
+/// Extra spread in degrees when moving at [`RUN_SPEED`] or faster
+const MOVING_SPREAD: f32 = 1.5;
+/// m/s
+const RUN_SPEED: f32 = 6.0;
+/// Spread multiplier when fully crouched
+const CROUCHED_SPREAD: f32 = 0.5;
+
+/// Recoil kicks, recovery and spread that grows with sustained fire and movement
+pub struct RecoilPlugin;
+
+impl Plugin for RecoilPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, update_accuracy.before(shoot_bullet));
+    }
+}
+
+/// How far off a player's shots currently go
+#[derive(Component)]
+pub struct Accuracy {
+    /// Degrees of spread built up by firing
+    pub bloom: f32,
+    /// Degrees of spread from moving or being airborne
+    pub movement: f32,
+    /// Multiplier from crouching
+    pub stance: f32,
+    /// Multiplier from aiming down the sights
+    pub aim: f32,
+    /// Shots since fire was last let go, for the recoil pattern
+    pub shots: usize,
+    /// Pitch and yaw kicked in radians, not yet recovered
+    pub kick: Vec2,
+}
+
+impl Default for Accuracy {
+    fn default() -> Self {
+        Self {
+            bloom: 0.0,
+            movement: 0.0,
+            stance: 1.0,
+            aim: 1.0,
+            shots: 0,
+            kick: Vec2::ZERO,
+        }
+    }
+}
+
+impl Accuracy {
+    /// A fire mode's spread with bloom and movement on top, in degrees
+    pub fn spread(&self, base: (f32, f32)) -> (f32, f32) {
+        let extra = self.bloom + self.movement;
+        let scale = self.stance * self.aim;
+        ((base.0 + extra) * scale, (base.1 + extra) * scale)
+    }
+
+    /// Kicks the view by the next step of the pattern and blooms the spread
+    pub fn fire(&mut self, recoil: &Recoil, input: &mut GoldenControllerInput) {
+        let step = recoil
+            .pattern
+            .get(self.shots.min(recoil.pattern.len().saturating_sub(1)))
+            .copied();
+        if let Some((pitch, yaw)) = step {
+            let kick = Vec2::new(pitch.to_radians(), yaw.to_radians());
+            let limit = FRAC_PI_2 - ANGLE_EPSILON;
+            input.pitch = (input.pitch + kick.x).clamp(-limit, limit);
+            input.yaw -= kick.y;
+            self.kick += kick;
+        }
+        self.shots += 1;
+        self.bloom = (self.bloom + recoil.bloom).min(recoil.max_bloom);
+    }
+}
+
+/// System: recover from recoil, shrink the bloom and work out the movement spread
+#[allow(clippy::type_complexity)]
+fn update_accuracy(
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut query: Query<(
+        &mut GoldenControllerInput,
+        &GoldenControllerMutables,
+        &GoldenControllerSpatialHits,
+        &LinearVelocity,
+        &Weapon,
+        &mut Accuracy,
+    )>,
+) {
+    for (mut input, mutables, spatial_hits, velocity, weapon, mut accuracy) in &mut query {
+        if !input.fire {
+            accuracy.shots = 0;
+        }
+
+        let up = mutables.up_rotation * Vec3::Y;
+        let speed = velocity.reject_from_normalized(up).length();
+        accuracy.movement = (speed / RUN_SPEED).min(1.0) * MOVING_SPREAD;
+        if !spatial_hits.bottom_down && !mutables.zero_g {
+            accuracy.movement += AIRBORNE_SPREAD;
+        }
+        accuracy.stance = 1.0 - (1.0 - CROUCHED_SPREAD) * mutables.crouch_degree;
+
+        let Some(recoil) = weapon_defs.get(&weapon.def).map(|def| &def.recoil) else {
+            continue;
+        };
+        accuracy.bloom = (accuracy.bloom - recoil.bloom_recovery * DT).max(0.0);
+        // settle back toward where the player was aiming before the kicks
+        let back = accuracy
+            .kick
+            .clamp_length_max(recoil.recovery.to_radians() * DT);
+        let limit = FRAC_PI_2 - ANGLE_EPSILON;
+        input.pitch = (input.pitch - back.x).clamp(-limit, limit);
+        input.yaw += back.y;
+        accuracy.kick -= back;
+    }
+}
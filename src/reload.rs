@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+
+use crate::{
+    Carrier, DT, GoldenControllerInput, PlayerInventory, Reload, ReloadStage, ReloadStyle,
+    WEAPON_TOP, Weapon, WeaponDef, WeaponSlots, WeaponStateMachine, shoot_bullet,
+};
+
+/// Reloads definition driven weapons from the [`PlayerInventory`].
+/// Sprite driven weapons reload through their own states instead.
+pub struct ReloadPlugin;
+
+impl Plugin for ReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (load_new_weapons, reload_weapons)
+                .chain()
+                .before(shoot_bullet),
+        );
+    }
+}
+
+/// System: definition driven weapons come with a full magazine, taken from the reserve
+/// the first time they're carried
+fn load_new_weapons(
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut query: Query<(&Weapon, Option<&WeaponSlots>, &mut PlayerInventory)>,
+) {
+    for (weapon, slots, mut inventory) in &mut query {
+        let carried = slots
+            .into_iter()
+            .flat_map(|slots| slots.slots.iter().flatten());
+        for def in carried.chain([&weapon.def]) {
+            let Some(def) = weapon_defs.get(def).filter(|def| def.states.is_none()) else {
+                continue;
+            };
+            let Some(loaded_item) = def.loaded_item() else {
+                continue;
+            };
+            // a loaded item that was never given is a magazine that was never loaded
+            if inventory.items.contains_key(&loaded_item) {
+                continue;
+            }
+            let moved = take_reserve(def, &mut inventory, def.magazine_size);
+            inventory.give(&loaded_item, moved, Some(def.magazine_size));
+        }
+    }
+}
+
+/// System: start, step and interrupt reloads
+fn reload_weapons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut query: Query<(
+        &mut GoldenControllerInput,
+        &mut Weapon,
+        &mut PlayerInventory,
+        &WeaponStateMachine,
+        Option<&Carrier>,
+    )>,
+) {
+    for (mut input, mut weapon, mut inventory, machine, carrier) in &mut query {
+        // nothing happens while the weapon is being raised or lowered
+        if machine.y > WEAPON_TOP {
+            continue;
+        }
+        let Some(def) = weapon_defs
+            .get(&weapon.def)
+            .filter(|def| def.states.is_none())
+        else {
+            continue;
+        };
+        let Some(loaded_item) = def.loaded_item() else {
+            continue;
+        };
+        let loaded = inventory.count(&loaded_item);
+        let reserve = def.reserve(&inventory).unwrap_or(u32::MAX);
+        let carrying = carrier.is_some_and(|carrier| carrier.held.is_some());
+        let mut play_reload_sound = false;
+
+        match weapon.reload {
+            None => {
+                // pulling the trigger on an empty weapon reloads it too
+                let wants =
+                    std::mem::take(&mut input.reload) || (input.fire && loaded < def.ammo_use);
+                if !wants || carrying || loaded >= def.magazine_size || reserve == 0 {
+                    continue;
+                }
+                weapon.reload = Some(match def.reload {
+                    ReloadStyle::Magazine { tactical, empty } => {
+                        play_reload_sound = true;
+                        Reload {
+                            stage: ReloadStage::Magazine,
+                            timer: if loaded > 0 { tactical } else { empty },
+                        }
+                    }
+                    ReloadStyle::PerShell { start, .. } => Reload {
+                        stage: ReloadStage::Opening,
+                        timer: start,
+                    },
+                });
+            }
+            Some(mut reload) => {
+                let end = match def.reload {
+                    ReloadStyle::PerShell { end, .. } => end,
+                    ReloadStyle::Magazine { .. } => 0.0,
+                };
+                // firing with rounds to spare or grabbing something interrupts, the rounds already
+                // loaded stay loaded and an interrupted magazine swap keeps the old magazine
+                let interrupted = carrying || (input.fire && loaded >= def.ammo_use);
+                if interrupted {
+                    reload = match reload.stage {
+                        ReloadStage::Magazine => {
+                            weapon.reload = None;
+                            continue;
+                        }
+                        ReloadStage::Opening | ReloadStage::Loading => Reload {
+                            stage: ReloadStage::Closing,
+                            timer: end,
+                        },
+                        ReloadStage::Closing => reload,
+                    };
+                }
+
+                reload.timer -= DT;
+                weapon.reload = Some(reload);
+                if reload.timer > 0.0 {
+                    continue;
+                }
+                weapon.reload = match (reload.stage, def.reload) {
+                    (ReloadStage::Magazine, _) => {
+                        let moved = take_reserve(
+                            def,
+                            &mut inventory,
+                            def.magazine_size.saturating_sub(loaded),
+                        );
+                        inventory.give(&loaded_item, moved, Some(def.magazine_size));
+                        None
+                    }
+                    (ReloadStage::Opening, ReloadStyle::PerShell { shell, .. }) => Some(Reload {
+                        stage: ReloadStage::Loading,
+                        timer: shell,
+                    }),
+                    (ReloadStage::Loading, ReloadStyle::PerShell { shell, .. }) => {
+                        let moved = take_reserve(def, &mut inventory, 1);
+                        inventory.give(&loaded_item, moved, Some(def.magazine_size));
+                        play_reload_sound = moved > 0;
+                        let full = loaded + moved >= def.magazine_size;
+                        let out = def.reserve(&inventory) == Some(0);
+                        Some(if full || out {
+                            Reload {
+                                stage: ReloadStage::Closing,
+                                timer: end,
+                            }
+                        } else {
+                            Reload {
+                                stage: ReloadStage::Loading,
+                                timer: shell,
+                            }
+                        })
+                    }
+                    _ => None,
+                };
+            }
+        }
+
+        if let Some(sound) = def.sounds.reload.as_ref().filter(|_| play_reload_sound) {
+            commands.spawn((
+                AudioPlayer::new(asset_server.load::<AudioSource>(sound)),
+                PlaybackSettings::DESPAWN,
+            ));
+        }
+    }
+}
+
+/// Takes up to `amount` reserve rounds, returns how many there were
+fn take_reserve(def: &WeaponDef, inventory: &mut PlayerInventory, amount: u32) -> u32 {
+    match &def.ammo_type {
+        Some(ammo_type) => inventory.take(ammo_type, amount),
+        None => amount,
+    }
+}
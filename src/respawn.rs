@@ -0,0 +1,290 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{
+    Armor, Died, GoldenControllerInput, GoldenControllerMutables, Health, LogicalPlayer,
+    PlayerInventory, TriggerEnter, TriggerFilter, TriggerVolume, VolumeKill, fps_controller_move,
+};
+
+pub struct RespawnPlugin;
+
+impl Plugin for RespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnSettings>()
+            .add_event::<PlayerDeath>()
+            .add_event::<PlayerRespawned>()
+            .add_systems(
+                Update,
+                (
+                    remember_spawn_armor,
+                    kill_volume_deaths,
+                    damage_deaths,
+                    activate_checkpoints,
+                    start_death,
+                    respawn_players,
+                )
+                    .chain(),
+            )
+            .add_systems(FixedUpdate, hold_dead_players.before(fps_controller_move));
+    }
+}
+
+/// What happens to the [`PlayerInventory`] when a player respawns
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum InventoryPolicy {
+    #[default]
+    Keep,
+    Reset,
+}
+
+#[derive(Resource)]
+pub struct RespawnSettings {
+    /// Seconds between dying and respawning
+    pub delay: f32,
+    pub inventory: InventoryPolicy,
+}
+
+impl Default for RespawnSettings {
+    fn default() -> Self {
+        Self {
+            delay: 2.0,
+            inventory: InventoryPolicy::Keep,
+        }
+    }
+}
+
+/// A place players can respawn at, the player faces along the spawn point's forward
+#[derive(Component, Default)]
+pub struct SpawnPoint {
+    pub tags: Vec<String>,
+    /// `None` lets every team use it
+    pub team: Option<u8>,
+}
+
+/// A player-only trigger that makes the player respawn here once touched.
+/// Touching a checkpoint with a lower order than the active one does nothing.
+#[derive(Component, Default)]
+#[require(TriggerVolume = TriggerVolume { filter: TriggerFilter::PLAYERS })]
+pub struct Checkpoint {
+    pub order: u32,
+}
+
+/// Put this on a logical player to have it go through the respawn flow
+#[derive(Component, Default)]
+pub struct Respawnable {
+    pub team: Option<u8>,
+    /// If set, only spawn points carrying this tag are used
+    pub spawn_tag: Option<String>,
+    pub checkpoint: Option<Entity>,
+}
+
+/// The armor a player spawned with, given back on every respawn
+#[derive(Component)]
+struct SpawnArmor(Armor);
+
+/// Present while the player is waiting to respawn
+#[derive(Component)]
+pub struct Dead {
+    pub timer: Timer,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerDeath {
+    pub entity: Entity,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerRespawned {
+    pub entity: Entity,
+    pub spawn_point: Entity,
+}
+
+fn kill_volume_deaths(mut kills: EventReader<VolumeKill>, mut deaths: EventWriter<PlayerDeath>) {
+    for kill in kills.read() {
+        deaths.write(PlayerDeath {
+            entity: kill.entity,
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn remember_spawn_armor(
+    mut commands: Commands,
+    players: Query<(Entity, &Armor), (Added<Armor>, With<Respawnable>)>,
+) {
+    for (entity, armor) in &players {
+        commands.entity(entity).insert(SpawnArmor(armor.clone()));
+    }
+}
+
+/// Only players go through the respawn flow, anything else that dies is someone else's business
+fn damage_deaths(
+    mut died: EventReader<Died>,
+    mut deaths: EventWriter<PlayerDeath>,
+    players: Query<(), (With<LogicalPlayer>, With<Respawnable>)>,
+) {
+    for died in died.read() {
+        if !players.contains(died.entity) {
+            continue;
+        }
+        deaths.write(PlayerDeath {
+            entity: died.entity,
+        });
+    }
+}
+
+fn activate_checkpoints(
+    mut enters: EventReader<TriggerEnter>,
+    checkpoints: Query<&Checkpoint>,
+    mut players: Query<&mut Respawnable, Without<Dead>>,
+) {
+    for enter in enters.read() {
+        let Ok(checkpoint) = checkpoints.get(enter.trigger) else {
+            continue;
+        };
+        let Ok(mut respawnable) = players.get_mut(enter.entity) else {
+            continue;
+        };
+        let current_order = respawnable
+            .checkpoint
+            .and_then(|current| checkpoints.get(current).ok())
+            .map(|current| current.order);
+        if current_order.is_none_or(|order| checkpoint.order >= order) {
+            respawnable.checkpoint = Some(enter.trigger);
+        }
+    }
+}
+
+fn start_death(
+    mut commands: Commands,
+    mut deaths: EventReader<PlayerDeath>,
+    settings: Res<RespawnSettings>,
+    players: Query<(), (With<Respawnable>, Without<Dead>)>,
+) {
+    for death in deaths.read() {
+        // dying twice before respawning only counts once
+        if players.contains(death.entity) {
+            commands.entity(death.entity).insert(Dead {
+                timer: Timer::from_seconds(settings.delay, TimerMode::Once),
+            });
+        }
+    }
+}
+
+/// Dead players can't move, the physics body just keeps falling or sliding
+fn hold_dead_players(mut query: Query<&mut GoldenControllerInput, With<Dead>>) {
+    for mut input in &mut query {
+        input.movement = Vec3::ZERO;
+        input.jump = false;
+        input.crouch = false;
+        input.lean = 0.0;
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn respawn_players(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<RespawnSettings>,
+    mut next_spawn: Local<usize>,
+    mut respawned: EventWriter<PlayerRespawned>,
+    spawn_points: Query<(Entity, &SpawnPoint, &GlobalTransform)>,
+    checkpoints: Query<&GlobalTransform, With<Checkpoint>>,
+    mut players: Query<
+        (
+            Entity,
+            &mut Dead,
+            &Respawnable,
+            &mut Transform,
+            &mut LinearVelocity,
+            &mut GoldenControllerMutables,
+            &mut GoldenControllerInput,
+            Option<&mut Health>,
+            Option<&mut Armor>,
+            Option<&SpawnArmor>,
+            Option<&mut PlayerInventory>,
+        ),
+        With<LogicalPlayer>,
+    >,
+) {
+    for (
+        entity,
+        mut dead,
+        respawnable,
+        mut transform,
+        mut velocity,
+        mut controller_mutables,
+        mut input,
+        health,
+        armor,
+        spawn_armor,
+        inventory,
+    ) in &mut players
+    {
+        if !dead.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        // the checkpoint wins, otherwise cycle through the matching spawn points
+        let spawn = respawnable
+            .checkpoint
+            .and_then(|checkpoint| Some((checkpoint, *checkpoints.get(checkpoint).ok()?)))
+            .or_else(|| {
+                let candidates: Vec<_> = spawn_points
+                    .iter()
+                    .filter(|(_, point, _)| {
+                        point.team.is_none() || point.team == respawnable.team
+                    })
+                    .filter(|(_, point, _)| {
+                        respawnable
+                            .spawn_tag
+                            .as_ref()
+                            .is_none_or(|tag| point.tags.contains(tag))
+                    })
+                    .collect();
+                if candidates.is_empty() {
+                    return None;
+                }
+                *next_spawn = (*next_spawn + 1) % candidates.len();
+                let (point, _, global) = candidates[*next_spawn];
+                Some((point, *global))
+            });
+        let Some((spawn_point, spawn_transform)) = spawn else {
+            warn!("no spawn point for player {entity}, staying dead");
+            continue;
+        };
+
+        let (_, rotation, translation) = spawn_transform.to_scale_rotation_translation();
+        let (yaw, _, _) = rotation.to_euler(EulerRot::YXZ);
+        transform.translation = translation;
+        velocity.0 = Vec3::ZERO;
+
+        let sensitivity = controller_mutables.sensitivity;
+        *controller_mutables = GoldenControllerMutables {
+            sensitivity,
+            ..default()
+        };
+        *input = GoldenControllerInput {
+            yaw,
+            pitch: 0.0,
+            ..default()
+        };
+        if let Some(mut health) = health {
+            health.current = health.max;
+        }
+        if let Some((mut armor, spawn_armor)) = armor.zip(spawn_armor) {
+            *armor = spawn_armor.0.clone();
+        }
+        if let Some(mut inventory) =
+            inventory.filter(|_| settings.inventory == InventoryPolicy::Reset)
+        {
+            *inventory = PlayerInventory::default();
+        }
+
+        commands.entity(entity).remove::<Dead>();
+        respawned.write(PlayerRespawned {
+            entity,
+            spawn_point,
+        });
+    }
+}
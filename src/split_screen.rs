@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::PrimaryWindow;
+
+use crate::RenderPlayer;
+
+pub struct SplitScreenPlugin;
+
+impl Plugin for SplitScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, split_screen_viewports);
+    }
+}
+
+/// Put on a [`RenderPlayer`] camera, decides which part of the window it renders to.
+/// 0 is the top left, with two players the window is split top and bottom,
+/// with three or four it is split into quarters.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LocalPlayer {
+    pub index: u32,
+}
+
+fn split_screen_viewports(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &LocalPlayer), With<RenderPlayer>>,
+) {
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    let window_size = window.physical_size();
+    let count = cameras.iter().count();
+    let (columns, rows) = if count <= 2 { (1, 2) } else { (2, 2) };
+    let cell = UVec2::new(window_size.x / columns, window_size.y / rows);
+
+    for (mut camera, local_player) in &mut cameras {
+        let order = local_player.index as isize;
+        if camera.order != order {
+            camera.order = order;
+        }
+
+        // a minimised window has no room for a split, render nothing until it's back
+        let active = count <= 1 || cell.min_element() > 0;
+        if camera.is_active != active {
+            camera.is_active = active;
+        }
+        if !active {
+            continue;
+        }
+
+        // extra players past the last cell share it rather than rendering off screen
+        let slot = local_player.index.min(columns * rows - 1);
+        let viewport =
+            (count > 1).then(|| (UVec2::new(slot % columns, slot / columns) * cell, cell));
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+        // only touch the camera when the layout changed, so change detection stays quiet
+        if current != viewport {
+            camera.viewport = viewport.map(|(physical_position, physical_size)| Viewport {
+                physical_position,
+                physical_size,
+                ..default()
+            });
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{Bullet, ControllerImpulse, LogicalPlayer};
+
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TriggerEnter>()
+            .add_event::<TriggerExit>()
+            .add_event::<TriggerStay>()
+            .add_event::<VolumeKill>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    trigger_events,
+                    (jump_pads, teleporters, kill_volumes),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Which kinds of entities a [`TriggerVolume`] reacts to
+#[derive(Clone, Copy, Debug)]
+pub struct TriggerFilter {
+    pub players: bool,
+    pub bullets: bool,
+    /// Any other dynamic rigid body, like the cubes you can push around
+    pub props: bool,
+}
+
+impl TriggerFilter {
+    pub const ALL: Self = Self {
+        players: true,
+        bullets: true,
+        props: true,
+    };
+    pub const PLAYERS: Self = Self {
+        players: true,
+        bullets: false,
+        props: false,
+    };
+}
+
+impl Default for TriggerFilter {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// A sensor collider that sends [`TriggerEnter`], [`TriggerExit`] and [`TriggerStay`]
+/// for every entity passing its filter
+#[derive(Component, Default)]
+#[require(Sensor, CollisionEventsEnabled, CollidingEntities)]
+pub struct TriggerVolume {
+    pub filter: TriggerFilter,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TriggerEnter {
+    pub trigger: Entity,
+    pub entity: Entity,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TriggerExit {
+    pub trigger: Entity,
+    pub entity: Entity,
+}
+
+/// Sent every fixed tick for each entity still inside the trigger
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TriggerStay {
+    pub trigger: Entity,
+    pub entity: Entity,
+}
+
+/// Launches whatever enters it, players go through [`ControllerImpulse::launch`]
+#[derive(Component)]
+#[require(TriggerVolume)]
+pub struct JumpPad {
+    pub velocity: Vec3,
+}
+
+#[derive(Component)]
+#[require(TriggerVolume)]
+pub struct Teleporter {
+    pub destination: Vec3,
+    pub keep_velocity: bool,
+}
+
+/// Despawns props and bullets, players get a [`VolumeKill`] instead
+#[derive(Component, Default)]
+#[require(TriggerVolume)]
+pub struct KillVolume;
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct VolumeKill {
+    pub entity: Entity,
+    pub volume: Entity,
+}
+
+fn trigger_events(
+    mut started: EventReader<CollisionStarted>,
+    mut ended: EventReader<CollisionEnded>,
+    mut enter_writer: EventWriter<TriggerEnter>,
+    mut exit_writer: EventWriter<TriggerExit>,
+    mut stay_writer: EventWriter<TriggerStay>,
+    triggers: Query<(Entity, &TriggerVolume, &CollidingEntities)>,
+    kinds: Query<(Has<LogicalPlayer>, Has<Bullet>, Option<&RigidBody>)>,
+) {
+    let passes = |filter: &TriggerFilter, entity: Entity| {
+        let Ok((player, bullet, body)) = kinds.get(entity) else {
+            return false;
+        };
+        if player {
+            filter.players
+        } else if bullet {
+            filter.bullets
+        } else {
+            filter.props && body.is_some_and(|body| body.is_dynamic())
+        }
+    };
+    // a collision pair can come in either order, so find out which side is the trigger
+    let sort = |a: Entity, b: Entity| {
+        if let Ok((trigger, volume, _)) = triggers.get(a) {
+            passes(&volume.filter, b).then_some((trigger, b))
+        } else if let Ok((trigger, volume, _)) = triggers.get(b) {
+            passes(&volume.filter, a).then_some((trigger, a))
+        } else {
+            None
+        }
+    };
+
+    for CollisionStarted(a, b) in started.read() {
+        if let Some((trigger, entity)) = sort(*a, *b) {
+            enter_writer.write(TriggerEnter { trigger, entity });
+        }
+    }
+    for CollisionEnded(a, b) in ended.read() {
+        if let Some((trigger, entity)) = sort(*a, *b) {
+            exit_writer.write(TriggerExit { trigger, entity });
+        }
+    }
+    for (trigger, volume, colliding) in &triggers {
+        for &entity in colliding.iter() {
+            if passes(&volume.filter, entity) {
+                stay_writer.write(TriggerStay { trigger, entity });
+            }
+        }
+    }
+}
+
+fn jump_pads(
+    mut enters: EventReader<TriggerEnter>,
+    mut impulses: EventWriter<ControllerImpulse>,
+    pads: Query<&JumpPad>,
+    mut bodies: Query<(Has<LogicalPlayer>, &mut LinearVelocity)>,
+) {
+    for enter in enters.read() {
+        let Ok(pad) = pads.get(enter.trigger) else {
+            continue;
+        };
+        let Ok((player, mut velocity)) = bodies.get_mut(enter.entity) else {
+            continue;
+        };
+        if player {
+            impulses.write(ControllerImpulse::launch(enter.entity, pad.velocity));
+        } else {
+            velocity.0 = pad.velocity;
+        }
+    }
+}
+
+fn teleporters(
+    mut enters: EventReader<TriggerEnter>,
+    teleporters: Query<&Teleporter>,
+    mut bodies: Query<(&mut Transform, Option<&mut LinearVelocity>)>,
+) {
+    for enter in enters.read() {
+        let Ok(teleporter) = teleporters.get(enter.trigger) else {
+            continue;
+        };
+        let Ok((mut transform, velocity)) = bodies.get_mut(enter.entity) else {
+            continue;
+        };
+        transform.translation = teleporter.destination;
+        if let Some(mut velocity) = velocity.filter(|_| !teleporter.keep_velocity) {
+            velocity.0 = Vec3::ZERO;
+        }
+    }
+}
+
+fn kill_volumes(
+    mut commands: Commands,
+    mut enters: EventReader<TriggerEnter>,
+    mut kills: EventWriter<VolumeKill>,
+    volumes: Query<(), With<KillVolume>>,
+    players: Query<(), With<LogicalPlayer>>,
+) {
+    for enter in enters.read() {
+        if !volumes.contains(enter.trigger) {
+            continue;
+        }
+        if players.contains(enter.entity) {
+            kills.write(VolumeKill {
+                entity: enter.entity,
+                volume: enter.trigger,
+            });
+        } else {
+            commands.entity(enter.entity).despawn();
+        }
+    }
+}
@@ -1,13 +1,12 @@
 use avian3d::prelude::LinearVelocity;
 use bevy::prelude::*;
-use bevy::window::WindowResized;
 use bevy::{
     asset::RenderAssetUsages,
     render::render_resource::{Extent3d, TextureDimension, TextureFormat},
 };
 
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::Color;
 use ratatui::widgets::Gauge;
 use ratatui::{
@@ -17,20 +16,51 @@ use ratatui::{
 
 use soft_ratatui::{Bdf, SoftBackend};
 
-use crate::{GoldenControllerKeys, LogicalPlayer, PlayerInventory, PlayerStats};
+use crate::{
+    Accuracy, Aim, Armor, DamageTaken, Health, Interactable, InteractionFocus, LogicalPlayer,
+    PlayerInventory, RenderPlayer, WEAPON_TOP, Weapon, WeaponDef, WeaponStateMachine,
+};
 
 pub struct GoldenUI;
 static FONT_BDF: &str = include_str!("../assets/spleen-12x24.bdf");
 
 impl Plugin for GoldenUI {
     fn build(&self, app: &mut App) {
-        app.init_resource::<SoftTerminal>()
-            .add_systems(Startup, (setup_crosshair, setup_gun, ratatui_setup))
-            .add_systems(Update, (ui_example_system, handle_resize_events));
+        app.add_event::<DamageTaken>()
+            .add_event::<HudMessage>()
+            .add_systems(
+                Update,
+                (
+                    setup_player_hud,
+                    handle_resize_events,
+                    receive_hud_messages,
+                    ui_example_system,
+                    draw_weapon_sprites,
+                    spread_crosshairs,
+                    show_sights,
+                    show_hitmarkers,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Every player camera gets its own crosshair, gun and ratatui HUD, so split-screen works
+fn setup_player_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    cameras: Query<Entity, Added<RenderPlayer>>,
+) {
+    for camera in &cameras {
+        setup_crosshair(&mut commands, &asset_server, camera);
+        setup_gun(&mut commands, camera);
+        setup_scope(&mut commands, &mut images, camera);
+        ratatui_setup(&mut commands, &mut images, camera);
     }
 }
 
-fn setup_crosshair(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_crosshair(commands: &mut Commands, asset_server: &AssetServer, camera: Entity) {
     // Load the crosshair PNG
     let crosshair = asset_server.load("crosshair2.png");
 
@@ -44,57 +74,419 @@ fn setup_crosshair(mut commands: Commands, asset_server: Res<AssetServer>) {
             row_gap: Val::Px(0.0),
             ..default()
         })
+        .insert(UiTargetCamera(camera))
         .with_children(|parent| {
             parent.spawn((
                 ImageNode::new(crosshair),
                 Node {
-                    width: Val::Px(27.0),
-                    height: Val::Px(27.0),
+                    width: Val::Px(CROSSHAIR_SIZE),
+                    height: Val::Px(CROSSHAIR_SIZE),
                     ..default()
                 },
+                Crosshair { camera },
                 GlobalZIndex(0), // This ensures it's always on top
                                  // BackgroundColor(ANTIQUE_WHITE.into()),
                                  // Outline::new(Val::Px(8.0), Val::ZERO, CRIMSON.into()),
             ));
+            // just off the crosshair, so it doesn't push it out of the middle
+            parent.spawn((
+                Text::new(""),
+                TextFont::from_font_size(18.0),
+                TextColor(bevy::color::palettes::css::ORANGE_RED.into()),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(50.0),
+                    margin: UiRect::new(Val::Px(24.0), Val::ZERO, Val::Px(16.0), Val::ZERO),
+                    ..default()
+                },
+                Visibility::Hidden,
+                Hitmarker {
+                    camera,
+                    shown_for: 0.0,
+                },
+            ));
         });
 }
 
-fn setup_gun(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Load the crosshair PNG
-    let gun = asset_server.load("AshesWeaponsV357/Graphics/FAL/FAL1D.png");
-    //  let gun = asset_server.load("AshesWeaponsV357/Graphics/Glock/glock1.png");
-
-    // Force nearest-neighbor (pixel-perfect) sampling
-
+fn setup_gun(commands: &mut Commands, camera: Entity) {
+    let mut layer = Entity::PLACEHOLDER;
     commands
         .spawn(Node {
-            width: Val::Percent(80.0),
+            width: Val::Percent(100.0),
             height: Val::Percent(100.0),
-            flex_direction: FlexDirection::Column,
-            justify_content: JustifyContent::FlexEnd,
-            align_items: AlignItems::FlexEnd,
-            row_gap: Val::Px(0.0),
+            justify_content: JustifyContent::Center,
             ..default()
         })
+        .insert(UiTargetCamera(camera))
+        .with_children(|parent| {
+            // doom's 320x200 screen, stretched to 4:3 like it always was
+            parent
+                .spawn(Node {
+                    height: Val::Percent(100.0),
+                    aspect_ratio: Some(4.0 / 3.0),
+                    overflow: Overflow::clip(),
+                    ..default()
+                })
+                .with_children(|canvas| {
+                    layer = canvas
+                        .spawn(Node {
+                            position_type: PositionType::Absolute,
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        })
+                        .id();
+                });
+        });
+    commands.entity(layer).insert(WeaponSprite {
+        camera,
+        shown: None,
+        def: None,
+        images: Vec::new(),
+    });
+}
+
+/// Black bars either side of a square scope, covering the view when looking through it
+fn setup_scope(commands: &mut Commands, images: &mut Assets<Image>, camera: Entity) {
+    let black = BackgroundColor(bevy::color::palettes::css::BLACK.into());
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            Visibility::Hidden,
+            GlobalZIndex(1),
+            Scope { camera },
+        ))
+        .insert(UiTargetCamera(camera))
         .with_children(|parent| {
             parent.spawn((
-                ImageNode::new(gun),
                 Node {
-                    height: Val::Percent(50.0), // 25% of screen height
-                    //    width: Val::Percent(50.0),  // 25% of screen height
+                    flex_grow: 1.0,
+                    ..default()
+                },
+                black,
+            ));
+            parent.spawn((
+                ImageNode::new(images.add(scope_image())),
+                Node {
+                    height: Val::Percent(100.0),
+                    aspect_ratio: Some(1.0),
                     ..default()
                 },
-                GlobalZIndex(0), // BackgroundColor(ANTIQUE_WHITE.into()),
-                                 // Outline::new(Val::Px(8.0), Val::ZERO, CRIMSON.into()),
             ));
+            parent.spawn((
+                Node {
+                    flex_grow: 1.0,
+                    ..default()
+                },
+                black,
+            ));
+        });
+}
+
+/// A clear circle with a thin cross in it and black everywhere else
+fn scope_image() -> Image {
+    const SIZE: u32 = 512;
+    let center = SIZE as f32 / 2.0;
+    let radius = center - 2.0;
+    let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let offset = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center;
+            let distance = offset.length();
+            let cross = offset.x.abs() < 1.0 || offset.y.abs() < 1.0;
+            // the rim darkens over a few pixels instead of cutting off
+            let alpha = if cross {
+                1.0
+            } else {
+                ((distance - (radius - 24.0)) / 24.0).clamp(0.0, 1.0)
+            };
+            data.extend_from_slice(&[0, 0, 0, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// The scope overlay of a camera
+#[derive(Component)]
+struct Scope {
+    camera: Entity,
+}
+
+/// System that puts up the scope or takes the crosshair down once a player is fully aimed
+fn show_sights(
+    weapon_defs: Res<Assets<WeaponDef>>,
+    cameras: Query<&RenderPlayer>,
+    weapons: Query<(&Weapon, &Aim)>,
+    mut scopes: Query<(&Scope, &mut Visibility), Without<Crosshair>>,
+    mut crosshairs: Query<(&Crosshair, &mut Visibility), Without<Scope>>,
+) {
+    // whether the camera's player is fully aimed, and if so through a scope
+    let aimed = |camera: Entity| {
+        let render_player = cameras.get(camera).ok()?;
+        let (weapon, aim) = weapons.get(render_player.logical_entity).ok()?;
+        let def = weapon_defs.get(&weapon.def)?;
+        (aim.blend >= 1.0).then_some(def.sights.scope)
+    };
+    for (scope, mut visibility) in &mut scopes {
+        let shown = if aimed(scope.camera) == Some(true) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(shown);
+    }
+    for (crosshair, mut visibility) in &mut crosshairs {
+        let shown = if aimed(crosshair.camera).is_some() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(shown);
+    }
+}
+
+/// The first person weapon of a camera, drawn from its current state frame
+#[derive(Component)]
+struct WeaponSprite {
+    camera: Entity,
+    /// Lump currently drawn, rebuilt when it changes
+    shown: Option<String>,
+    /// Weapon whose patches are loaded
+    def: Option<AssetId<WeaponDef>>,
+    /// Keeps every patch of the weapon loaded, so frames don't wait on the disk mid animation
+    images: Vec<Handle<Image>>,
+}
+
+/// Shown for weapons without sprite states
+const STATIC_GUN: &str = "AshesWeaponsV357/Graphics/FAL/FAL1D.png";
+
+/// System that keeps each weapon layer on the frame its state machine is on
+fn draw_weapon_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    weapon_defs: Res<Assets<WeaponDef>>,
+    cameras: Query<&RenderPlayer>,
+    weapons: Query<(&Weapon, &WeaponStateMachine, &Aim)>,
+    mut layers: Query<(Entity, &mut WeaponSprite, &mut Node, &mut Visibility)>,
+) {
+    for (layer, mut sprite, mut node, mut visibility) in &mut layers {
+        let Some((weapon, machine, aim)) = cameras
+            .get(sprite.camera)
+            .ok()
+            .and_then(|render_player| weapons.get(render_player.logical_entity).ok())
+        else {
+            continue;
+        };
+        let Some(def) = weapon_defs.get(&weapon.def) else {
+            continue;
+        };
+        // the sights slide toward the middle of the screen as they come up
+        let (x, y) = def.sights.offset;
+        let left = Val::Percent(x * aim.blend / 3.2);
+        let top = Val::Percent((machine.y - WEAPON_TOP + y * aim.blend) / 2.0);
+        if node.top != top || node.left != left {
+            node.top = top;
+            node.left = left;
+        }
+        // looking through a scope there's no gun to see
+        let shown = if def.sights.scope && aim.blend >= 1.0 {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(shown);
+
+        let Some(states) = &def.states else {
+            if sprite.shown.as_deref() != Some(STATIC_GUN) {
+                commands
+                    .entity(layer)
+                    .despawn_related::<Children>()
+                    .with_child((
+                        ImageNode::new(asset_server.load(STATIC_GUN)),
+                        Node {
+                            position_type: PositionType::Absolute,
+                            right: Val::Percent(10.0),
+                            bottom: Val::Px(0.0),
+                            height: Val::Percent(50.0),
+                            ..default()
+                        },
+                        GlobalZIndex(0),
+                    ));
+                sprite.shown = Some(STATIC_GUN.to_string());
+            }
+            continue;
+        };
+        if sprite.def != Some(weapon.def.id()) {
+            sprite.def = Some(weapon.def.id());
+            sprite.images = states
+                .sprites
+                .values()
+                .flat_map(|layout| &layout.patches)
+                .map(|patch| asset_server.load(&patch.image))
+                .collect();
+        }
+        let lump = machine
+            .frame
+            .and_then(|frame| states.frames.get(frame))
+            .and_then(|frame| frame.lump());
+        if lump == sprite.shown {
+            continue;
+        }
+        let layout = lump.as_ref().and_then(|lump| states.sprites.get(lump));
+        let patches = layout.map_or(Some(Vec::new()), |layout| {
+            layout
+                .patches
+                .iter()
+                .map(|patch| {
+                    let image = asset_server.load::<Image>(&patch.image);
+                    let size = images.get(&image)?.size();
+                    Some((image, patch.position, size))
+                })
+                .collect::<Option<Vec<_>>>()
         });
+        // try again once every patch is loaded, so the size is known
+        let Some(patches) = patches else {
+            continue;
+        };
+
+        let mut entity = commands.entity(layer);
+        entity.despawn_related::<Children>();
+        if let Some(layout) = layout {
+            // doom places the canvas' offset at the left edge and WEAPONTOP down
+            let corner = Vec2::new(0.0, WEAPON_TOP) - layout.offset.as_vec2() / layout.scale;
+            entity.with_children(|parent| {
+                for (image, position, size) in patches {
+                    let min = corner + position.as_vec2() / layout.scale;
+                    let size = size.as_vec2() / layout.scale;
+                    parent.spawn((
+                        ImageNode::new(image),
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(min.x / 3.2),
+                            top: Val::Percent(min.y / 2.0),
+                            width: Val::Percent(size.x / 3.2),
+                            height: Val::Percent(size.y / 2.0),
+                            ..default()
+                        },
+                        GlobalZIndex(0),
+                    ));
+                }
+            });
+        }
+        sprite.shown = lump;
+    }
 }
 
-// Marker if you want to reference it later
+/// The crosshair of a camera, it opens up to show where shots can land
 #[derive(Component)]
-struct Crosshair;
+struct Crosshair {
+    camera: Entity,
+}
+
+/// Size of the crosshair image with no spread
+const CROSSHAIR_SIZE: f32 = 27.0;
+
+/// System that sizes each crosshair to the current spread of its player's weapon
+fn spread_crosshairs(
+    weapon_defs: Res<Assets<WeaponDef>>,
+    cameras: Query<(&Camera, &Projection, &RenderPlayer)>,
+    weapons: Query<(&Weapon, &Accuracy)>,
+    mut crosshairs: Query<(&Crosshair, &mut Node)>,
+) {
+    for (crosshair, mut node) in &mut crosshairs {
+        let Ok((camera, projection, render_player)) = cameras.get(crosshair.camera) else {
+            continue;
+        };
+        let (Projection::Perspective(perspective), Some(viewport)) =
+            (projection, camera.logical_viewport_size())
+        else {
+            continue;
+        };
+        let Some((mode, accuracy)) =
+            weapons
+                .get(render_player.logical_entity)
+                .ok()
+                .and_then(|(weapon, accuracy)| {
+                    let def = weapon_defs.get(&weapon.def)?;
+                    Some((def.fire_modes.get(weapon.mode)?, accuracy))
+                })
+        else {
+            continue;
+        };
+        let (spread_h, spread_v) = accuracy.spread(mode.spread);
+        // the spread angle projected onto the screen, fov is vertical
+        let spread = spread_h.max(spread_v).min(80.0).to_radians();
+        let radius = spread.tan() / (perspective.fov / 2.0).tan() * viewport.y / 2.0;
+        let size = Val::Px(CROSSHAIR_SIZE + 2.0 * radius);
+        if node.width != size {
+            node.width = size;
+            node.height = size;
+        }
+    }
+}
+
+/// Damage the player just dealt, next to the crosshair of a camera
+#[derive(Component)]
+struct Hitmarker {
+    camera: Entity,
+    /// Seconds it stays up
+    shown_for: f32,
+}
+
+/// Seconds a hitmarker stays up after a hit
+const HITMARKER_TIME: f32 = 0.4;
+
+/// System that shows the damage each player's latest shot did, all pellets and targets together
+fn show_hitmarkers(
+    time: Res<Time>,
+    mut taken: EventReader<DamageTaken>,
+    cameras: Query<&RenderPlayer>,
+    mut hitmarkers: Query<(&mut Hitmarker, &mut Text, &mut Visibility)>,
+) {
+    let taken: Vec<_> = taken.read().copied().collect();
+    for (mut hitmarker, mut text, mut visibility) in &mut hitmarkers {
+        let Ok(render_player) = cameras.get(hitmarker.camera) else {
+            continue;
+        };
+        let player = render_player.logical_entity;
+        // hurting yourself isn't a hit
+        let dealt: Vec<f32> = taken
+            .iter()
+            .filter(|taken| taken.source == Some(player) && taken.target != player)
+            .map(|taken| taken.amount + taken.absorbed)
+            .collect();
+        if !dealt.is_empty() {
+            text.0 = format!("{:.0}", dealt.iter().sum::<f32>());
+            hitmarker.shown_for = HITMARKER_TIME;
+        }
+        hitmarker.shown_for = (hitmarker.shown_for - time.delta_secs()).max(0.0);
+        let shown = if hitmarker.shown_for > 0.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(shown);
+    }
+}
 
-#[derive(Resource, Deref, DerefMut)]
+#[derive(Deref, DerefMut)]
 struct SoftTerminal(Terminal<SoftBackend<Bdf>>);
 impl Default for SoftTerminal {
     fn default() -> Self {
@@ -103,35 +495,109 @@ impl Default for SoftTerminal {
         Self(Terminal::new(backend).unwrap())
     }
 }
-#[derive(Resource)]
-struct MyRatatui(Handle<Image>);
+/// The ratatui HUD of one player camera
+#[derive(Component)]
+struct PlayerHud {
+    terminal: SoftTerminal,
+    image: Handle<Image>,
+    /// Viewport size the terminal was last fitted to
+    size: Vec2,
+    /// Printed message and seconds it stays up
+    message: Option<(String, f32)>,
+}
+
+/// Text printed across the middle of a player's HUD for a while, like ACS `Print`
+#[derive(Event, Clone, Debug)]
+pub struct HudMessage {
+    /// The logical player who sees it
+    pub player: Entity,
+    pub text: String,
+}
 
-/// System that reacts to window resize
-fn handle_resize_events(
-    mut resize_reader: EventReader<WindowResized>,
-    mut softatui: ResMut<SoftTerminal>,
+/// Seconds a [`HudMessage`] stays up, ZDoom's `con_midtime`
+const HUD_MESSAGE_TIME: f32 = 3.0;
+
+/// System that gives each HUD its player's latest message and takes old ones down
+fn receive_hud_messages(
+    time: Res<Time>,
+    mut messages: EventReader<HudMessage>,
+    mut huds: Query<(&mut PlayerHud, &RenderPlayer)>,
 ) {
-    for event in resize_reader.read() {
-        let cur_pix_width = softatui.backend().char_width;
-        let cur_pix_height = softatui.backend().char_height;
-        let av_wid = (event.width / cur_pix_width as f32) as u16;
-        let av_hei = (event.height / cur_pix_height as f32) as u16;
-        softatui.backend_mut().resize(av_wid, av_hei);
+    let messages: Vec<_> = messages.read().collect();
+    for (mut hud, render_player) in &mut huds {
+        if let Some(message) = messages
+            .iter()
+            .rfind(|message| message.player == render_player.logical_entity)
+        {
+            hud.message = Some((message.text.clone(), HUD_MESSAGE_TIME));
+            continue;
+        }
+        let expired = hud.message.as_mut().is_some_and(|(_, left)| {
+            *left -= time.delta_secs();
+            *left <= 0.0
+        });
+        if expired {
+            hud.message = None;
+        }
+    }
+}
+
+/// System that fits each HUD terminal to its camera viewport
+fn handle_resize_events(mut cameras: Query<(&Camera, &mut PlayerHud)>) {
+    for (camera, mut hud) in &mut cameras {
+        let Some(size) = camera.logical_viewport_size() else {
+            continue;
+        };
+        if size == hud.size {
+            continue;
+        }
+        hud.size = size;
+        let cur_pix_width = hud.terminal.backend().char_width;
+        let cur_pix_height = hud.terminal.backend().char_height;
+        let av_wid = (size.x / cur_pix_width as f32) as u16;
+        let av_hei = (size.y / cur_pix_height as f32) as u16;
+        hud.terminal.backend_mut().resize(av_wid, av_hei);
     }
 }
 // Render to the terminal and to egui , both are immediate mode
+#[allow(clippy::type_complexity)]
 fn ui_example_system(
-    mut softatui: ResMut<SoftTerminal>,
     mut images: ResMut<Assets<Image>>,
-    mut controller_query: Query<(&Transform, &LinearVelocity), With<LogicalPlayer>>,
-    my_handle: Res<MyRatatui>,
-    query: Query<(&PlayerStats, &PlayerInventory), With<GoldenControllerKeys>>,
+    mut huds: Query<(&mut PlayerHud, &RenderPlayer)>,
+    query: Query<
+        (
+            &LinearVelocity,
+            Option<&Health>,
+            Option<&Armor>,
+            &PlayerInventory,
+            Option<&InteractionFocus>,
+            Option<&Weapon>,
+        ),
+        With<LogicalPlayer>,
+    >,
+    interactables: Query<&Interactable>,
+    weapon_defs: Res<Assets<WeaponDef>>,
 ) {
-    let mut speed_text = String::new();
-    for (transform, velocity) in &mut controller_query {
-        speed_text = format!("spd: {:.2}", velocity.0.xz().length());
-    }
-    if let Ok((stats, inv)) = query.single() {
+    for (mut hud, render_player) in &mut huds {
+        let Ok((velocity, health, armor, inventory, focus, weapon)) =
+            query.get(render_player.logical_entity)
+        else {
+            continue;
+        };
+        let speed_text = format!("spd: {:.2}", velocity.0.xz().length());
+        let health = health.map(|health| (health.current, health.max));
+        let armor = armor.map(|armor| (armor.current, armor.max));
+        let ammo_text = weapon
+            .and_then(|weapon| Some((weapon, weapon_defs.get(&weapon.def)?)))
+            .map(|(weapon, def)| ammo_text(weapon, def, inventory))
+            .unwrap_or_default();
+        let prompt = focus
+            .and_then(|focus| focus.target)
+            .and_then(|target| interactables.get(target).ok())
+            .map(|interactable| format!("[use] {}", interactable.prompt));
+        let hud = &mut *hud;
+        let message = hud.message.as_ref().map(|(text, _)| text.clone());
+        let softatui = &mut hud.terminal;
         softatui
             .draw(|frame| {
                 let area = frame.area();
@@ -141,11 +607,25 @@ fn ui_example_system(
                     .direction(Direction::Vertical)
                     .constraints([
                         Constraint::Min(0),    // Top part takes the rest
+                        Constraint::Length(1), // Interaction prompt
                         Constraint::Length(1), // Bottom part is 3 characters high
                     ])
                     .split(area);
                 render_top_section(frame, chunks[0]);
-                render_bottom_bar(frame, chunks[1], speed_text);
+                render_top_section(frame, chunks[1]);
+                if let Some(message) = message {
+                    // a third of the way down, clear of the crosshair
+                    let line = Rect {
+                        y: chunks[0].y + chunks[0].height / 3,
+                        height: 1.min(chunks[0].height),
+                        ..chunks[0]
+                    };
+                    render_prompt(frame, line, message);
+                }
+                if let Some(prompt) = prompt {
+                    render_prompt(frame, chunks[1], prompt);
+                }
+                render_bottom_bar(frame, chunks[2], health, armor, speed_text, ammo_text);
             })
             .expect("epic fail");
 
@@ -156,7 +636,7 @@ fn ui_example_system(
             .rgb_pixmap
             .to_rgba_with_color_as_transparent(&(255, 0, 255));
 
-        let image = images.get_mut(&my_handle.0).expect("Image not found");
+        let image = images.get_mut(&hud.image).expect("Image not found");
         *image = Image::new(
             Extent3d {
                 width,
@@ -171,7 +651,45 @@ fn ui_example_system(
     }
 }
 
-fn render_bottom_bar(frame: &mut Frame<'_>, chunk: ratatui::prelude::Rect, speed_text: String) {
+/// Magazine over reserve, like `17 / 120`
+fn ammo_text(weapon: &Weapon, def: &WeaponDef, inventory: &PlayerInventory) -> String {
+    let reserve = def
+        .reserve(inventory)
+        .map_or("--".to_string(), |reserve| reserve.to_string());
+    let ammo = match def.loaded_item() {
+        Some(loaded) => format!("{} / {reserve}", inventory.count(&loaded)),
+        None => reserve,
+    };
+    match weapon.reload {
+        Some(_) => format!("{}\n{ammo} reloading", def.name),
+        None => format!("{}\n{ammo}", def.name),
+    }
+}
+
+/// Current over max, rounded up so a sliver of health never reads as 0
+fn gauge(name: &str, color: Color, value: Option<(f32, f32)>) -> Gauge<'static> {
+    let (current, max) = value.unwrap_or_default();
+    let ratio = if max > 0.0 {
+        (current / max).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Gauge::default()
+        .block(Block::new())
+        .gauge_style(color)
+        .on_dark_gray()
+        .ratio(ratio as f64)
+        .label(format!("{name} {}/{}", current.ceil(), max.ceil()))
+}
+
+fn render_bottom_bar(
+    frame: &mut Frame<'_>,
+    chunk: ratatui::prelude::Rect,
+    health: Option<(f32, f32)>,
+    armor: Option<(f32, f32)>,
+    speed_text: String,
+    ammo_text: String,
+) {
     // Split the frame into two parts
     let bar_chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -179,28 +697,11 @@ fn render_bottom_bar(frame: &mut Frame<'_>, chunk: ratatui::prelude::Rect, speed
         .split(chunk);
 
     // Bottom part with border and text
-    frame.render_widget(
-        Gauge::default()
-            .block(Block::new())
-            .gauge_style(Color::Blue)
-            .on_dark_gray()
-            .ratio(50.0 / 100.0)
-            .label("50/100"),
-        bar_chunks[0],
-    );
+    frame.render_widget(gauge("HP", Color::Red, health), bar_chunks[0]);
+    frame.render_widget(gauge("AR", Color::Green, armor), bar_chunks[1]);
     // Bottom part with border and text
     frame.render_widget(
-        Paragraph::new(
-            "mAAAAAeow\nAAAAAAAA\nAAAAAAAAabcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPRSTUWYXZQ",
-        )
-        .white()
-        .on_green()
-        .wrap(Wrap { trim: false }),
-        bar_chunks[1],
-    );
-    // Bottom part with border and text
-    frame.render_widget(
-        Paragraph::new("w░░░░░o▓▓▓▓of\n LOL")
+        Paragraph::new(ammo_text)
             .white()
             .on_black()
             .wrap(Wrap { trim: false }),
@@ -208,6 +709,17 @@ fn render_bottom_bar(frame: &mut Frame<'_>, chunk: ratatui::prelude::Rect, speed
     );
     frame.render_widget(Paragraph::new(speed_text).black(), bar_chunks[3]);
 }
+fn render_prompt(frame: &mut Frame<'_>, chunk: Rect, prompt: String) {
+    // centered and only as wide as the text, the rest of the line stays see-through
+    let width = (prompt.chars().count() as u16 + 2).min(chunk.width);
+    let area = Rect {
+        x: chunk.x + (chunk.width - width) / 2,
+        width,
+        ..chunk
+    };
+    frame.render_widget(Paragraph::new(prompt).centered().white().on_black(), area);
+}
+
 fn render_top_section(frame: &mut Frame<'_>, chunk: ratatui::prelude::Rect) {
     // Fill the top part with magenta
     frame.render_widget(
@@ -220,11 +732,8 @@ fn render_top_section(frame: &mut Frame<'_>, chunk: ratatui::prelude::Rect) {
     );
 }
 
-fn ratatui_setup(
-    mut commands: Commands,
-    softatui: ResMut<SoftTerminal>,
-    mut images: ResMut<Assets<Image>>,
-) {
+fn ratatui_setup(commands: &mut Commands, images: &mut Assets<Image>, camera: Entity) {
+    let softatui = SoftTerminal::default();
     let width = softatui.backend().get_pixmap_width() as u32;
     let height = softatui.backend().get_pixmap_height() as u32;
     let data = softatui
@@ -255,6 +764,7 @@ fn ratatui_setup(
             row_gap: Val::Px(0.0),
             ..default()
         })
+        .insert(UiTargetCamera(camera))
         .with_children(|parent| {
             parent.spawn((
                 ImageNode::new(handle.clone()),
@@ -267,5 +777,10 @@ fn ratatui_setup(
                                  // Outline::new(Val::Px(8.0), Val::ZERO, CRIMSON.into()),
             ));
         });
-    commands.insert_resource(MyRatatui(handle));
+    commands.entity(camera).insert(PlayerHud {
+        terminal: softatui,
+        image: handle,
+        size: Vec2::ZERO,
+        message: None,
+    });
 }
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    DecorateWeapons, HudMessage, Interactable, Interacted, PlayerInventory, Weapon, WeaponDef,
+    WeaponSlots, WeaponStateMachine,
+};
+
+/// Weapon tiers installed with modkits at workbenches, ported from the pack's `MODKITS.txt` ACS
+/// script. The upgraded weapon takes the old one's place in its slot and keeps its loaded rounds.
+pub struct UpgradePlugin;
+
+impl Plugin for UpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeaponUpgrades>()
+            .add_event::<Interacted>()
+            .add_event::<HudMessage>()
+            .add_systems(
+                Startup,
+                add_modkit_upgrades.run_if(resource_exists::<DecorateWeapons>),
+            )
+            .add_systems(Update, use_workbenches);
+    }
+}
+
+/// The next tier of each weapon that has one
+#[derive(Resource, Default)]
+pub struct WeaponUpgrades {
+    tiers: HashMap<AssetId<WeaponDef>, WeaponUpgrade>,
+}
+
+impl WeaponUpgrades {
+    /// Makes `upgrade` the next tier of `from`, replacing any it had
+    pub fn add(&mut self, from: &Handle<WeaponDef>, upgrade: WeaponUpgrade) {
+        self.tiers.insert(from.id(), upgrade);
+    }
+
+    pub fn get(&self, from: &Handle<WeaponDef>) -> Option<&WeaponUpgrade> {
+        self.tiers.get(&from.id())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WeaponUpgrade {
+    pub into: Handle<WeaponDef>,
+    /// Inventory item one install uses up
+    pub item: String,
+    /// Shown on the HUD once it's installed
+    pub message: String,
+    pub sound: Option<String>,
+}
+
+/// A bench that installs the next tier of the held weapon, using up a modkit
+#[derive(Component, Default)]
+#[require(Interactable::new("Install modkit"))]
+pub struct Workbench;
+
+/// System: the pack's modkit swaps, for the weapons on both ends that were imported
+fn add_modkit_upgrades(decorate: Res<DecorateWeapons>, mut upgrades: ResMut<WeaponUpgrades>) {
+    for upgrade in &decorate.upgrades {
+        let (Some(from), Some(into)) = (decorate.get(&upgrade.from), decorate.get(&upgrade.to))
+        else {
+            debug!(
+                "skipping the {} to {} upgrade, one of them wasn't imported",
+                upgrade.from, upgrade.to
+            );
+            continue;
+        };
+        upgrades.add(
+            &from,
+            WeaponUpgrade {
+                into,
+                item: upgrade.item.clone(),
+                message: upgrade.message.clone(),
+                sound: upgrade.sound.clone(),
+            },
+        );
+    }
+}
+
+/// System: using a workbench swaps the held weapon for its next tier, if there's a modkit for it
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn use_workbenches(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    weapon_defs: Res<Assets<WeaponDef>>,
+    upgrades: Res<WeaponUpgrades>,
+    mut interactions: EventReader<Interacted>,
+    mut messages: EventWriter<HudMessage>,
+    workbenches: Query<(), With<Workbench>>,
+    mut players: Query<(
+        &mut Weapon,
+        &mut WeaponStateMachine,
+        &mut PlayerInventory,
+        Option<&mut WeaponSlots>,
+    )>,
+) {
+    for interaction in interactions.read() {
+        if !workbenches.contains(interaction.target) {
+            continue;
+        }
+        let Ok((mut weapon, mut machine, mut inventory, slots)) =
+            players.get_mut(interaction.player)
+        else {
+            continue;
+        };
+        let mut message = |text: &str| {
+            messages.write(HudMessage {
+                player: interaction.player,
+                text: text.to_string(),
+            });
+        };
+        let Some(upgrade) = upgrades.get(&weapon.def) else {
+            message("There's nothing to install on this weapon");
+            continue;
+        };
+        let (Some(from), Some(into)) =
+            (weapon_defs.get(&weapon.def), weapon_defs.get(&upgrade.into))
+        else {
+            continue;
+        };
+        if inventory.take(&upgrade.item, 1) == 0 {
+            message("You need a modkit");
+            continue;
+        }
+        carry_rounds(&mut inventory, from, into);
+
+        if let Some(mut slots) = slots {
+            for def in slots.slots.iter_mut().flatten() {
+                if *def == weapon.def {
+                    *def = upgrade.into.clone();
+                }
+            }
+            let slots = &mut *slots;
+            for def in [&mut slots.last, &mut slots.pending].into_iter().flatten() {
+                if *def == weapon.def {
+                    *def = upgrade.into.clone();
+                }
+            }
+        }
+        *weapon = Weapon::new(upgrade.into.clone());
+        // comes up from select like any other switch
+        *machine = WeaponStateMachine::default();
+
+        if let Some(sound) = &upgrade.sound {
+            commands.spawn((
+                AudioPlayer::new(asset_server.load::<AudioSource>(sound)),
+                PlaybackSettings::DESPAWN,
+            ));
+        }
+        message(&upgrade.message);
+    }
+}
+
+/// Moves the rounds loaded in `from` into `into`, whatever doesn't fit goes back to the reserve
+fn carry_rounds(inventory: &mut PlayerInventory, from: &WeaponDef, into: &WeaponDef) {
+    let Some(loaded) = from.loaded_item() else {
+        return;
+    };
+    let rounds = inventory.take(&loaded, u32::MAX);
+    let kept = into.loaded_item().map_or(0, |loaded| {
+        inventory.give(&loaded, rounds, Some(into.magazine_size))
+    });
+    if let Some(reserve) = into.ammo_type.as_ref().or(from.ammo_type.as_ref()) {
+        inventory.give(reserve, rounds - kept, None);
+    }
+}
@@ -0,0 +1,382 @@
+use avian3d::prelude::*;
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use crate::{
+    ControllerSuspended, DT, GoldenControllerInput, GoldenControllerMutables, Interactable,
+    Interacted, LogicalPlayer,
+};
+
+pub struct VehiclePlugin;
+
+impl Plugin for VehiclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                setup_vehicle_sounds,
+                mount_vehicles,
+                follow_vehicle_seat,
+                vehicle_engine_sounds,
+            )
+                .chain(),
+        )
+        .add_systems(FixedUpdate, vehicle_physics);
+    }
+}
+
+/// A raycast-wheel motorcycle, ported from the pack's `VEHICLE.txt` ACS script.
+/// Forward is -Z, the two wheels sit on the local Z axis.
+#[derive(Component)]
+#[require(
+    RigidBody::Dynamic,
+    VehicleMutables,
+    ExternalForce,
+    ExternalTorque,
+    Interactable::new("Ride motorcycle")
+)]
+pub struct Vehicle {
+    pub wheel_base: f32,
+    /// Length of the suspension ray from the body center, the wheel is unloaded past this
+    pub suspension_rest: f32,
+    pub suspension_stiffness: f32,
+    pub suspension_damping: f32,
+
+    pub engine_force: f32,
+    pub brake_force: f32,
+    pub max_speed: f32,
+    /// How fast the sideways slip of a wheel is killed, higher is grippier
+    pub grip: f32,
+
+    pub max_steer: f32,
+    pub steer_speed: f32,
+    /// Max roll into turns, same role as the 0.3 tilt limit in the ACS script
+    pub max_lean: f32,
+    pub upright_stiffness: f32,
+    pub upright_damping: f32,
+
+    pub idle_rpm: f32,
+    pub max_rpm: f32,
+
+    /// Where the rider's logical player sits, in vehicle space
+    pub seat_offset: Vec3,
+    /// Jumping off only works below this speed, like the `Speedo < 20` check in the script
+    pub dismount_max_speed: f32,
+}
+
+impl Default for Vehicle {
+    fn default() -> Self {
+        Self {
+            wheel_base: 1.4,
+            suspension_rest: 0.8,
+            suspension_stiffness: 12000.0,
+            suspension_damping: 1200.0,
+
+            engine_force: 3000.0,
+            brake_force: 5000.0,
+            max_speed: 30.0,
+            grip: 8.0,
+
+            max_steer: 0.5,
+            steer_speed: 2.0,
+            max_lean: 0.3,
+            upright_stiffness: 40.0,
+            upright_damping: 8.0,
+
+            idle_rpm: 1200.0,
+            max_rpm: 9000.0,
+
+            seat_offset: Vec3::new(0.0, 0.6, 0.2),
+            dismount_max_speed: 5.0,
+        }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct VehicleMutables {
+    pub rider: Option<Entity>,
+    pub throttle: f32,
+    /// Front wheel angle in radians, positive turns left
+    pub steer: f32,
+    pub rpm: f32,
+    idle_sound: Option<Entity>,
+    throttle_sound: Option<Entity>,
+}
+
+/// On a logical player while it rides a vehicle
+#[derive(Component)]
+pub struct Riding {
+    pub vehicle: Entity,
+}
+
+fn setup_vehicle_sounds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut vehicles: Query<(Entity, &mut VehicleMutables), Added<Vehicle>>,
+) {
+    for (entity, mut vehicle_mutables) in &mut vehicles {
+        let mut engine_loop = |path: &'static str| {
+            commands
+                .spawn((
+                    AudioPlayer::new(asset_server.load(path)),
+                    PlaybackSettings::LOOP.with_volume(Volume::Linear(0.0)),
+                    ChildOf(entity),
+                ))
+                .id()
+        };
+        vehicle_mutables.idle_sound =
+            Some(engine_loop("AshesWeaponsV357/Sounds/bike/DSBIKIDL.ogg"));
+        vehicle_mutables.throttle_sound =
+            Some(engine_loop("AshesWeaponsV357/Sounds/bike/DSBIKTHR.ogg"));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn mount_vehicles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut interactions: EventReader<Interacted>,
+    mut players: Query<
+        (
+            Entity,
+            &mut GoldenControllerInput,
+            &mut Transform,
+            &mut LinearVelocity,
+            Option<&Riding>,
+        ),
+        With<LogicalPlayer>,
+    >,
+    mut vehicles: Query<
+        (
+            Entity,
+            &Vehicle,
+            &mut VehicleMutables,
+            &Transform,
+            &LinearVelocity,
+        ),
+        Without<LogicalPlayer>,
+    >,
+) {
+    for interaction in interactions.read() {
+        let Ok((_, _, mut vehicle_mutables, ..)) = vehicles.get_mut(interaction.target) else {
+            continue;
+        };
+        if vehicle_mutables.rider.is_some() {
+            continue;
+        }
+        vehicle_mutables.rider = Some(interaction.player);
+
+        // the body is parked while riding, the vehicle does the physics
+        commands.entity(interaction.player).insert((
+            Riding {
+                vehicle: interaction.target,
+            },
+            ControllerSuspended,
+            RigidBodyDisabled,
+            ColliderDisabled,
+        ));
+        commands.spawn((
+            AudioPlayer::new(asset_server.load("AshesWeaponsV357/Sounds/bike/DSBIKSTA.ogg")),
+            PlaybackSettings::DESPAWN,
+        ));
+    }
+
+    for (player, mut input, mut transform, mut velocity, riding) in &mut players {
+        let Some(riding) = riding else {
+            continue;
+        };
+        let Ok((_, vehicle, mut vehicle_mutables, vehicle_transform, vehicle_velocity)) =
+            vehicles.get_mut(riding.vehicle)
+        else {
+            continue;
+        };
+        let slow_enough = vehicle_velocity.length() < vehicle.dismount_max_speed;
+        if !(slow_enough && (input.interact || input.jump)) {
+            continue;
+        }
+
+        // step off to the right of the bike, facing where it faces
+        transform.translation = vehicle_transform.transform_point(vehicle.seat_offset)
+            + vehicle_transform.right() * 1.0;
+        velocity.0 = vehicle_velocity.0;
+        let (yaw, _, _) = vehicle_transform.rotation.to_euler(EulerRot::YXZ);
+        input.yaw = yaw;
+        vehicle_mutables.rider = None;
+
+        commands.entity(player).remove::<(
+            Riding,
+            ControllerSuspended,
+            RigidBodyDisabled,
+            ColliderDisabled,
+        )>();
+        commands.spawn((
+            AudioPlayer::new(asset_server.load("AshesWeaponsV357/Sounds/bike/DSBIKSTO.ogg")),
+            PlaybackSettings::DESPAWN,
+        ));
+    }
+}
+
+/// Keeps the rider on the seat, the render player follows the logical player so the
+/// camera rides along and only mouse pitch is left to the player
+fn follow_vehicle_seat(
+    mut players: Query<
+        (&Riding, &mut Transform, &mut GoldenControllerMutables),
+        With<LogicalPlayer>,
+    >,
+    vehicles: Query<(&Vehicle, &Transform), Without<LogicalPlayer>>,
+) {
+    for (riding, mut transform, mut controller_mutables) in &mut players {
+        let Ok((vehicle, vehicle_transform)) = vehicles.get(riding.vehicle) else {
+            continue;
+        };
+        transform.translation = vehicle_transform.transform_point(vehicle.seat_offset);
+        transform.rotation = vehicle_transform.rotation;
+        controller_mutables.up_rotation = Quat::IDENTITY;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn vehicle_physics(
+    spatial_query_pipeline: Res<SpatialQueryPipeline>,
+    riders: Query<&GoldenControllerInput>,
+    mut vehicles: Query<(
+        Entity,
+        &Vehicle,
+        &mut VehicleMutables,
+        &Transform,
+        &LinearVelocity,
+        &AngularVelocity,
+        &ComputedMass,
+        &mut ExternalForce,
+        &mut ExternalTorque,
+    )>,
+) {
+    for (
+        entity,
+        vehicle,
+        mut vehicle_mutables,
+        transform,
+        velocity,
+        angular_velocity,
+        mass,
+        mut force,
+        mut torque,
+    ) in &mut vehicles
+    {
+        let (throttle, steer_input) = vehicle_mutables
+            .rider
+            .and_then(|rider| riders.get(rider).ok())
+            .map_or((0.0, 0.0), |input| (input.movement.z, input.movement.x));
+        vehicle_mutables.throttle = throttle;
+
+        let up = transform.up();
+        let forward = transform.forward();
+        let forward_speed = velocity.dot(*forward);
+
+        // handlebars turn slower at speed, like the script's tiny yaw steps
+        let target_steer = -steer_input * vehicle.max_steer / (1.0 + forward_speed.abs() / 10.0);
+        let steer_step = vehicle.steer_speed * DT;
+        vehicle_mutables.steer +=
+            (target_steer - vehicle_mutables.steer).clamp(-steer_step, steer_step);
+
+        let mass = mass.value();
+        let filter = SpatialQueryFilter::default().with_excluded_entities([entity]);
+        let mut total_force = Vec3::ZERO;
+        let mut total_torque = Vec3::ZERO;
+        let mut apply = |force: Vec3, point: Vec3| {
+            total_force += force;
+            total_torque += (point - transform.translation).cross(force);
+        };
+
+        for (front, z) in [
+            (true, -vehicle.wheel_base / 2.0),
+            (false, vehicle.wheel_base / 2.0),
+        ] {
+            let attach = transform.transform_point(Vec3::new(0.0, 0.0, z));
+            let Some(hit) = spatial_query_pipeline.cast_ray(
+                attach,
+                -up,
+                vehicle.suspension_rest,
+                true,
+                &filter,
+            ) else {
+                continue;
+            };
+            let contact = attach - up * hit.distance;
+            let point_velocity =
+                velocity.0 + angular_velocity.0.cross(attach - transform.translation);
+
+            // suspension spring-damper along the body up, it can only push
+            let compression = vehicle.suspension_rest - hit.distance;
+            let suspension = (vehicle.suspension_stiffness * compression
+                - vehicle.suspension_damping * point_velocity.dot(*up))
+            .max(0.0);
+            apply(up * suspension, contact);
+
+            let wheel_forward = if front {
+                Quat::from_axis_angle(*up, vehicle_mutables.steer) * forward
+            } else {
+                forward
+            };
+            let wheel_right = wheel_forward.cross(*up);
+
+            // each wheel carries half the bike and kills its own sideways slip
+            let slip = point_velocity.dot(wheel_right);
+            apply(-wheel_right * slip * vehicle.grip * mass / 2.0, contact);
+
+            if !front {
+                let drive = if throttle > 0.0 && forward_speed < vehicle.max_speed {
+                    throttle * vehicle.engine_force
+                } else if throttle < 0.0 && forward_speed > 0.5 {
+                    throttle * vehicle.brake_force
+                } else if throttle < 0.0 && forward_speed > -vehicle.max_speed * 0.2 {
+                    // reverse is slow
+                    throttle * vehicle.engine_force * 0.3
+                } else {
+                    0.0
+                };
+                apply(*wheel_forward * drive, contact);
+            }
+        }
+
+        // stay upright and roll into turns, the ACS tilt
+        let flat_forward = Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero();
+        let lean = (vehicle_mutables.steer * forward_speed * 0.1)
+            .clamp(-vehicle.max_lean, vehicle.max_lean);
+        let target_up = Quat::from_axis_angle(flat_forward, -lean) * Vec3::Y;
+        let tilt_velocity = angular_velocity.0 - up * angular_velocity.0.dot(*up);
+        total_torque += (up.cross(target_up) * vehicle.upright_stiffness
+            - tilt_velocity * vehicle.upright_damping)
+            * mass;
+
+        force.set_force(total_force);
+        torque.set_torque(total_torque);
+
+        let speed_ratio = (forward_speed.abs() / vehicle.max_speed).min(1.0);
+        let target_rpm = vehicle.idle_rpm
+            + (vehicle.max_rpm - vehicle.idle_rpm) * (0.7 * speed_ratio + 0.3 * throttle.abs());
+        vehicle_mutables.rpm += (target_rpm - vehicle_mutables.rpm) * (5.0 * DT).min(1.0);
+    }
+}
+
+fn vehicle_engine_sounds(
+    vehicles: Query<(&Vehicle, &VehicleMutables)>,
+    mut sinks: Query<&mut AudioSink>,
+) {
+    for (vehicle, vehicle_mutables) in &vehicles {
+        // engine is off without a rider
+        let running = vehicle_mutables.rider.is_some() as u8 as f32;
+        let throttle = vehicle_mutables.throttle.abs();
+        let pitch = (vehicle_mutables.rpm / vehicle.idle_rpm).clamp(0.8, 2.5);
+
+        for (sound, volume) in [
+            (vehicle_mutables.idle_sound, running * (1.0 - throttle)),
+            (vehicle_mutables.throttle_sound, running * throttle),
+        ] {
+            if let Some(mut sink) = sound.and_then(|sound| sinks.get_mut(sound).ok()) {
+                sink.set_volume(Volume::Linear(volume));
+                sink.set_speed(pitch);
+            }
+        }
+    }
+}
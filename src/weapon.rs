@@ -0,0 +1,390 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    Accuracy, Aim, DamageType, GoldenControllerInput, MUZZLE_FLASH_LIGHT, PlayerInventory,
+    ShotKind, WeaponStateMachine, WeaponStates, shoot_bullet,
+};
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<WeaponDef>()
+            .init_asset_loader::<WeaponDefLoader>()
+            .add_systems(Update, reload_weapon_defs)
+            .add_systems(FixedUpdate, switch_fire_modes.before(shoot_bullet));
+    }
+}
+
+/// Everything about how a weapon shoots, loaded from `*.weapon.ron`.
+/// Edits to the file are picked up while the game runs.
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct WeaponDef {
+    pub name: String,
+    /// The fire button uses `fire_modes[Weapon::mode]`
+    pub fire_modes: Vec<FireMode>,
+    /// Inventory ammo this weapon draws from, `None` for weapons that never run out
+    #[serde(default)]
+    pub ammo_type: Option<String>,
+    /// Rounds used per shot
+    #[serde(default = "one")]
+    pub ammo_use: u32,
+    /// 0 means the weapon feeds straight from the reserve
+    #[serde(default)]
+    pub magazine_size: u32,
+    /// How rounds get from the reserve into the magazine
+    #[serde(default)]
+    pub reload: ReloadStyle,
+    #[serde(default)]
+    pub recoil: Recoil,
+    #[serde(default)]
+    pub sounds: WeaponSounds,
+    #[serde(default)]
+    pub sights: Sights,
+    /// GLDEFS light flashed at the muzzle on every shot
+    #[serde(default = "muzzle_flash")]
+    pub muzzle_light: Option<String>,
+    /// Sprite animation and action states, for weapons imported from DECORATE
+    #[serde(skip)]
+    pub states: Option<WeaponStates>,
+}
+
+impl WeaponDef {
+    /// Inventory item holding the loaded rounds, `None` when the weapon feeds straight from the reserve.
+    /// DECORATE weapons use their `Weapon.AmmoType1`, like `GlockLoaded`.
+    pub fn loaded_item(&self) -> Option<String> {
+        (self.magazine_size > 0).then(|| {
+            self.states
+                .as_ref()
+                .and_then(|states| states.primary_ammo.clone())
+                .unwrap_or_else(|| format!("{}loaded", self.name.to_lowercase()))
+        })
+    }
+
+    /// Reserve rounds left, `None` for weapons that never run out
+    pub fn reserve(&self, inventory: &PlayerInventory) -> Option<u32> {
+        self.ammo_type
+            .as_ref()
+            .map(|ammo_type| inventory.count(ammo_type))
+    }
+
+    /// Rounds loaded or in reserve, weapons that never run out always have some
+    pub fn has_ammo(&self, inventory: &PlayerInventory) -> bool {
+        self.loaded_item()
+            .is_some_and(|item| inventory.count(&item) > 0)
+            || self.reserve(inventory) != Some(0)
+    }
+
+    /// Takes one shot's worth of rounds, false when there aren't enough
+    pub fn take_ammo(&self, inventory: &mut PlayerInventory) -> bool {
+        let Some(item) = self.loaded_item().or_else(|| self.ammo_type.clone()) else {
+            return true;
+        };
+        if inventory.count(&item) < self.ammo_use {
+            return false;
+        }
+        inventory.take(&item, self.ammo_use);
+        true
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct FireMode {
+    pub trigger: TriggerMode,
+    /// Seconds between shots
+    pub cooldown: f32,
+    #[serde(default = "one")]
+    pub pellets: u32,
+    /// Horizontal and vertical spread in degrees, each pellet lands
+    /// anywhere within plus or minus these, like `A_FireBullets`
+    #[serde(default)]
+    pub spread: (f32, f32),
+    /// Where in the spread each pellet goes
+    #[serde(default)]
+    pub pattern: SpreadPattern,
+    /// GLDEFS light carried by each projectile
+    #[serde(default)]
+    pub light: Option<String>,
+    /// Per pellet
+    pub damage: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub shot: ShotKind,
+}
+
+/// Where the pellets of one shot land inside the spread
+#[derive(Deserialize, Clone, PartialEq, Debug, Default)]
+pub enum SpreadPattern {
+    /// Anywhere, each pellet on its own
+    #[default]
+    Random,
+    /// Spread out evenly the same way every shot, the first pellet in the middle
+    Even,
+    /// Points as fractions of the spread, -1 to 1, reused in order past the last one
+    Fixed(Vec<(f32, f32)>),
+}
+
+impl SpreadPattern {
+    /// Yaw and pitch in degrees of pellet `index` out of `pellets`
+    pub fn offset(
+        &self,
+        index: u32,
+        pellets: u32,
+        (spread_h, spread_v): (f32, f32),
+        rng: &mut impl Rng,
+    ) -> (f32, f32) {
+        match self {
+            SpreadPattern::Random => (
+                rng.gen_range(-spread_h..=spread_h),
+                rng.gen_range(-spread_v..=spread_v),
+            ),
+            SpreadPattern::Even => {
+                // a sunflower, golden angle turns with the radius growing by area
+                let radius = (index as f32 / pellets.max(1) as f32).sqrt();
+                let angle = index as f32 * 2.399_963;
+                (
+                    radius * angle.cos() * spread_h,
+                    radius * angle.sin() * spread_v,
+                )
+            }
+            SpreadPattern::Fixed(points) => points
+                .get(index as usize % points.len().max(1))
+                .map_or((0.0, 0.0), |(h, v)| (h * spread_h, v * spread_v)),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TriggerMode {
+    /// One shot per trigger pull
+    Semi,
+    /// `count` shots per trigger pull, one every cooldown
+    Burst { count: u32 },
+    /// Keeps firing while the trigger is held
+    Auto,
+}
+
+/// Timings in seconds
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ReloadStyle {
+    /// Swaps the whole magazine, `empty` is usually slower since the action has to be worked too
+    Magazine { tactical: f32, empty: f32 },
+    /// One round at a time like a tube fed shotgun, firing stops it after the current shell
+    PerShell { start: f32, shell: f32, end: f32 },
+}
+
+impl Default for ReloadStyle {
+    fn default() -> Self {
+        Self::Magazine {
+            tactical: 1.5,
+            empty: 2.0,
+        }
+    }
+}
+
+/// How a weapon kicks the view and blooms its spread, angles in degrees
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Recoil {
+    /// Pitch up and yaw right of each shot in a string, the last one repeats
+    pub pattern: Vec<(f32, f32)>,
+    /// Degrees per second the view settles back after a kick
+    pub recovery: f32,
+    /// Spread added by each shot
+    pub bloom: f32,
+    pub max_bloom: f32,
+    /// Degrees per second the bloom shrinks
+    pub bloom_recovery: f32,
+}
+
+impl Default for Recoil {
+    fn default() -> Self {
+        Self {
+            pattern: vec![(0.5, 0.0)],
+            recovery: 6.0,
+            bloom: 0.3,
+            max_bloom: 3.0,
+            bloom_recovery: 4.0,
+        }
+    }
+}
+
+/// How a weapon aims down its sights. The default only hides the crosshair,
+/// weapons spell out how much they zoom, tighten and move.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Sights {
+    /// The view narrows to the hip field of view divided by this, like `A_ZoomFactor`
+    pub zoom: f32,
+    /// Seconds to bring the sights up
+    pub time: f32,
+    /// Spread multiplier when fully aimed
+    pub spread: f32,
+    /// Weapon sprite shift in 320x200 screen units when fully aimed, so the sights line up
+    pub offset: (f32, f32),
+    /// Look through a scope instead of the weapon sprite
+    pub scope: bool,
+}
+
+impl Default for Sights {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            time: 0.2,
+            spread: 1.0,
+            offset: (0.0, 0.0),
+            scope: false,
+        }
+    }
+}
+
+/// Asset paths, relative to the assets folder
+#[derive(Deserialize, Clone, Default, Debug)]
+pub struct WeaponSounds {
+    #[serde(default)]
+    pub fire: Option<String>,
+    #[serde(default)]
+    pub dry_fire: Option<String>,
+    #[serde(default)]
+    pub reload: Option<String>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn muzzle_flash() -> Option<String> {
+    Some(MUZZLE_FLASH_LIGHT.to_string())
+}
+
+/// The weapon a logical player fires with
+#[derive(Component)]
+#[require(WeaponStateMachine, Accuracy, Aim)]
+pub struct Weapon {
+    pub def: Handle<WeaponDef>,
+    /// Index into `WeaponDef::fire_modes`
+    pub mode: usize,
+    /// Seconds until the next shot is allowed
+    pub cooldown: f32,
+    /// Shots left in the current burst
+    pub burst_left: u32,
+    /// Fire was held last tick, for semi auto and burst trigger pulls
+    pub trigger_held: bool,
+    /// The reload in progress, the weapon doesn't fire until it's done
+    pub reload: Option<Reload>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Reload {
+    pub stage: ReloadStage,
+    /// Seconds until the stage is done
+    pub timer: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReloadStage {
+    /// Rounds move when the new magazine is in
+    Magazine,
+    /// Per shell reloads open up, load shells one by one and close again
+    Opening,
+    Loading,
+    Closing,
+}
+
+impl Weapon {
+    pub fn new(def: Handle<WeaponDef>) -> Self {
+        Self {
+            def,
+            mode: 0,
+            cooldown: 0.0,
+            burst_left: 0,
+            trigger_held: false,
+            reload: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WeaponDefLoader;
+
+#[derive(Debug, Error)]
+pub enum WeaponDefLoaderError {
+    #[error("could not read weapon definition: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse weapon definition: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for WeaponDefLoader {
+    type Asset = WeaponDef;
+    type Settings = ();
+    type Error = WeaponDefLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<WeaponDef, WeaponDefLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut def: WeaponDef = ron::de::from_bytes(&bytes)?;
+        // a spread is a half angle either side, the sign means nothing
+        for mode in &mut def.fire_modes {
+            mode.spread = (mode.spread.0.abs(), mode.spread.1.abs());
+        }
+        Ok(def)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapon.ron"]
+    }
+}
+
+/// System: the fire mode bind steps through the weapon's fire modes
+fn switch_fire_modes(
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut query: Query<(&mut GoldenControllerInput, &mut Weapon)>,
+) {
+    for (mut input, mut weapon) in &mut query {
+        if !std::mem::take(&mut input.switch_fire_mode) {
+            continue;
+        }
+        let Some(def) = weapon_defs.get(&weapon.def) else {
+            continue;
+        };
+        if def.fire_modes.len() < 2 {
+            continue;
+        }
+        weapon.mode = (weapon.mode + 1) % def.fire_modes.len();
+        // a burst doesn't carry over into the next mode
+        weapon.burst_left = 0;
+    }
+}
+
+/// A reloaded definition can have fewer fire modes than before
+fn reload_weapon_defs(
+    mut events: EventReader<AssetEvent<WeaponDef>>,
+    defs: Res<Assets<WeaponDef>>,
+    mut weapons: Query<&mut Weapon>,
+) {
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let Some(def) = defs.get(*id) else {
+            continue;
+        };
+        info!("reloaded weapon {}", def.name);
+        for mut weapon in &mut weapons {
+            if weapon.def.id() == *id && weapon.mode >= def.fire_modes.len() {
+                weapon.mode = 0;
+            }
+        }
+    }
+}
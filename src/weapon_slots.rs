@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+
+use crate::{
+    GoldenControllerInput, PlayerInventory, Weapon, WeaponDef, WeaponSelect, WeaponStateMachine,
+    step_weapon_states,
+};
+
+/// Number key, wheel and quick switching between the weapons in a player's [`WeaponSlots`].
+/// The held weapon is lowered first, the next one comes up from the bottom.
+pub struct WeaponSlotsPlugin;
+
+impl Plugin for WeaponSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                select_weapons.before(step_weapon_states),
+                swap_lowered_weapons.after(step_weapon_states),
+            ),
+        );
+    }
+}
+
+/// The weapons a player carries by slot, like `Player.WeaponSlot`
+#[derive(Component, Default)]
+pub struct WeaponSlots {
+    /// Indexed by slot number, 0 to 9
+    pub slots: Vec<Vec<Handle<WeaponDef>>>,
+    /// Comes up once the held weapon is down
+    pub pending: Option<Handle<WeaponDef>>,
+    /// Held before the current weapon, for quick switching
+    pub last: Option<Handle<WeaponDef>>,
+}
+
+impl WeaponSlots {
+    /// Every weapon in number row order, slot 1 first and slot 0 last
+    fn ordered(&self) -> Vec<&Handle<WeaponDef>> {
+        (1..self.slots.len())
+            .chain(0..1)
+            .filter_map(|slot| self.slots.get(slot))
+            .flatten()
+            .collect()
+    }
+
+    /// The weapon a selection lands on from `current`, skipping the ones without ammo
+    fn pick(
+        &self,
+        select: WeaponSelect,
+        current: &Handle<WeaponDef>,
+        has_ammo: impl Fn(&Handle<WeaponDef>) -> bool,
+    ) -> Option<Handle<WeaponDef>> {
+        let usable = |def: &&Handle<WeaponDef>| *def != current && has_ammo(def);
+        match select {
+            WeaponSelect::Slot(slot) => {
+                // pressing the held weapon's slot again moves on to the next one in it
+                let slot = self.slots.get(slot as usize)?;
+                let start = slot
+                    .iter()
+                    .position(|def| def == current)
+                    .map_or(0, |index| index + 1);
+                cycle(slot.iter(), start).find(usable).cloned()
+            }
+            WeaponSelect::Next | WeaponSelect::Previous => {
+                let mut order = self.ordered();
+                if select == WeaponSelect::Previous {
+                    order.reverse();
+                }
+                let start = order
+                    .iter()
+                    .position(|def| *def == current)
+                    .map_or(0, |index| index + 1);
+                cycle(order.into_iter(), start).find(usable).cloned()
+            }
+            WeaponSelect::Last => self.last.as_ref().filter(usable).cloned(),
+        }
+    }
+}
+
+/// Every item once, starting at `start` and wrapping around
+fn cycle<T>(
+    items: impl ExactSizeIterator<Item = T> + Clone,
+    start: usize,
+) -> impl Iterator<Item = T> {
+    let len = items.len();
+    items.cycle().skip(start).take(len)
+}
+
+/// System: turn weapon selections into a pending weapon and start lowering the held one
+fn select_weapons(
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut query: Query<(
+        &mut GoldenControllerInput,
+        &Weapon,
+        &mut WeaponSlots,
+        &mut WeaponStateMachine,
+        Option<&PlayerInventory>,
+    )>,
+) {
+    for (mut input, weapon, mut slots, mut machine, inventory) in &mut query {
+        let Some(select) = input.weapon_select.take() else {
+            continue;
+        };
+        // weapons that aren't loaded yet or players without an inventory never run dry
+        let has_ammo = |def: &Handle<WeaponDef>| {
+            weapon_defs
+                .get(def)
+                .zip(inventory)
+                .is_none_or(|(def, inventory)| def.has_ammo(inventory))
+        };
+        // selecting again while lowering carries on from the weapon on its way
+        let current = slots.pending.clone().unwrap_or_else(|| weapon.def.clone());
+        let Some(next) = slots.pick(select, &current, has_ammo) else {
+            continue;
+        };
+        slots.pending = Some(next);
+        machine.lower = true;
+    }
+}
+
+/// System: once the held weapon is down, bring up the pending one.
+/// Loaded rounds live in the inventory, so they stay with the weapon put away.
+fn swap_lowered_weapons(
+    mut query: Query<(&mut Weapon, &mut WeaponSlots, &mut WeaponStateMachine)>,
+) {
+    for (mut weapon, mut slots, mut machine) in &mut query {
+        if !machine.lowered() {
+            continue;
+        }
+        let Some(next) = slots.pending.take() else {
+            continue;
+        };
+        if next != weapon.def {
+            slots.last = Some(weapon.def.clone());
+        }
+        *weapon = Weapon::new(next);
+        // starts over from select even when switching back to the same weapon
+        *machine = WeaponStateMachine::default();
+    }
+}
@@ -0,0 +1,586 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::asset::AssetId;
+use bevy::prelude::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::{
+    ANGLE_EPSILON, Carrier, DT, FireMode, GoldenControllerInput, PlayerInventory, ShotKind,
+    StateAction, StateNext, TICS_PER_SECOND, TriggerMode, Weapon, WeaponDef, WeaponStates,
+    shoot_bullet, sound_name,
+};
+
+/// Psprite heights of a raised and a lowered weapon, in 320x200 screen units
+pub const WEAPON_TOP: f32 = 32.0;
+pub const WEAPON_BOTTOM: f32 = 128.0;
+/// How far each `A_Raise` and `A_Lower` moves the weapon unless they say otherwise
+const RAISE_SPEED: f32 = 6.0;
+/// Zero tic frames chained in one tic before giving up, reload loops can take a few dozen
+const MAX_CHAINED_FRAMES: usize = 1024;
+
+pub struct WeaponStatePlugin;
+
+impl Plugin for WeaponStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WeaponStateAction>()
+            .add_systems(FixedUpdate, step_weapon_states.before(shoot_bullet));
+    }
+}
+
+/// An action function a weapon frame ran, for anything that wants to react to
+/// the ones the state machine only knows by name, like `A_GunFlash` or `A_AlertMonsters`
+#[derive(Event, Clone, Debug)]
+pub struct WeaponStateAction {
+    /// The logical player holding the weapon
+    pub player: Entity,
+    pub action: StateAction,
+}
+
+/// Where a sprite driven weapon is in its [`WeaponStates`]
+#[derive(Component)]
+pub struct WeaponStateMachine {
+    /// Current index into `WeaponStates::frames`, `None` once the states stop
+    pub frame: Option<usize>,
+    /// Tics left on the current frame, -1 is forever
+    pub tics: i32,
+    /// Psprite height, [`WEAPON_TOP`] when raised
+    pub y: f32,
+    /// Shots `A_FireBullets` asked for since the fire path last ran
+    pub shots: Vec<FireMode>,
+    /// A weapon switch is waiting, lower as soon as the weapon allows
+    pub lower: bool,
+    /// Seconds the states stay frozen, a melee hit holds the swing for a moment
+    pub hit_stop: f32,
+    /// Set by `A_WeaponReady` until the frame changes
+    ready: Option<ReadyFlags>,
+    /// Fire has been held since the last shot started, `+NOAUTOFIRE` weapons wait for a release
+    attack_down: bool,
+    /// Shots in a row through `A_ReFire`, the first shot of a single bullet weapon is accurate
+    refire: u32,
+    /// Seconds not yet stepped
+    tic_time: f32,
+    /// The states this machine is stepping, a different definition starts over from `Select`
+    def: Option<AssetId<WeaponDef>>,
+}
+
+impl Default for WeaponStateMachine {
+    fn default() -> Self {
+        Self {
+            frame: None,
+            tics: 0,
+            y: WEAPON_BOTTOM,
+            shots: Vec::new(),
+            lower: false,
+            hit_stop: 0.0,
+            ready: None,
+            attack_down: false,
+            refire: 0,
+            tic_time: 0.0,
+            def: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ReadyFlags {
+    fire: bool,
+    reload: bool,
+    switch: bool,
+    user1: bool,
+}
+
+/// What follows a frame
+enum Transition {
+    To(usize),
+    Stop,
+    Stay,
+}
+
+/// Everything a tic can read and change besides the machine itself
+struct Tic<'a> {
+    states: &'a WeaponStates,
+    def: &'a WeaponDef,
+    mode: &'a FireMode,
+    /// Fire is held, and not to throw something carried
+    fire: bool,
+    input: &'a mut GoldenControllerInput,
+    inventory: &'a mut PlayerInventory,
+    /// Asset paths to play
+    sounds: Vec<String>,
+    /// Every action run, for [`WeaponStateAction`]
+    actions: Vec<StateAction>,
+}
+
+/// System: step every sprite driven weapon at 35 tics per second,
+/// weapons without states only slide up, or down while switching away, with or without an inventory
+#[allow(clippy::type_complexity)]
+pub(crate) fn step_weapon_states(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    weapon_defs: Res<Assets<WeaponDef>>,
+    mut state_actions: EventWriter<WeaponStateAction>,
+    mut query: Query<(
+        Entity,
+        &Weapon,
+        &mut WeaponStateMachine,
+        &mut GoldenControllerInput,
+        Option<&mut PlayerInventory>,
+        Option<&Carrier>,
+    )>,
+) {
+    for (player, weapon, mut machine, mut input, inventory, carrier) in &mut query {
+        let Some(def) = weapon_defs.get(&weapon.def) else {
+            continue;
+        };
+        let switched = machine.def != Some(weapon.def.id());
+        if switched {
+            *machine = WeaponStateMachine {
+                def: Some(weapon.def.id()),
+                ..default()
+            };
+        }
+        let (Some(states), Some(mode)) = (&def.states, def.fire_modes.get(weapon.mode)) else {
+            machine.tic_time += DT;
+            while machine.tic_time >= 1.0 / TICS_PER_SECOND {
+                machine.tic_time -= 1.0 / TICS_PER_SECOND;
+                machine.y = if machine.lower {
+                    (machine.y + RAISE_SPEED).min(WEAPON_BOTTOM)
+                } else {
+                    (machine.y - RAISE_SPEED).max(WEAPON_TOP)
+                };
+            }
+            continue;
+        };
+        // the states count and take ammo, they only run for players with an inventory
+        let Some(mut inventory) = inventory else {
+            continue;
+        };
+        let mut tic = Tic {
+            states,
+            def,
+            mode,
+            fire: input.fire && !carrier.is_some_and(Carrier::blocks_fire),
+            input: &mut input,
+            inventory: &mut inventory,
+            sounds: Vec::new(),
+            actions: Vec::new(),
+        };
+
+        if switched {
+            let start = states.label("select").or(states.label("ready"));
+            machine.enter(&mut tic, start);
+        }
+
+        if machine.hit_stop > 0.0 {
+            machine.hit_stop = (machine.hit_stop - DT).max(0.0);
+        } else {
+            machine.tic_time += DT;
+        }
+        while machine.tic_time >= 1.0 / TICS_PER_SECOND {
+            machine.tic_time -= 1.0 / TICS_PER_SECOND;
+            machine.tic(&mut tic);
+        }
+
+        for sound in tic.sounds {
+            commands.spawn((
+                AudioPlayer::new(asset_server.load::<AudioSource>(sound)),
+                PlaybackSettings::DESPAWN,
+            ));
+        }
+        for action in tic.actions {
+            state_actions.write(WeaponStateAction { player, action });
+        }
+    }
+}
+
+impl WeaponStateMachine {
+    /// All the way down after a switch was asked for, the next weapon can come up
+    pub fn lowered(&self) -> bool {
+        self.lower && self.y >= WEAPON_BOTTOM
+    }
+
+    fn tic(&mut self, tic: &mut Tic) {
+        let states = tic.states;
+        if !tic.fire {
+            self.attack_down = false;
+        }
+        // like P_CheckWeaponFire, the ready frame is left before it counts down
+        if let Some(ready) = self.ready {
+            if ready.switch && self.lower {
+                match states.label("deselect") {
+                    Some(deselect) => self.enter(tic, Some(deselect)),
+                    None => {
+                        self.enter(tic, None);
+                        self.y = WEAPON_BOTTOM;
+                    }
+                }
+                return;
+            }
+            let semi = tic.mode.trigger == TriggerMode::Semi;
+            if ready.fire && tic.fire && !(semi && self.attack_down) {
+                self.attack_down = true;
+                self.refire = 0;
+                self.enter(tic, states.label("fire"));
+                return;
+            }
+            let reload = states
+                .label("reload")
+                .filter(|_| ready.reload && tic.input.reload);
+            if reload.is_some() {
+                // one reload per press
+                tic.input.reload = false;
+                self.enter(tic, reload);
+                return;
+            }
+            let user1 = states
+                .label("user1")
+                .filter(|_| ready.user1 && tic.input.melee);
+            if user1.is_some() {
+                self.enter(tic, user1);
+                return;
+            }
+        }
+        let Some(frame) = self.frame else {
+            // stopped states have nothing to lower with
+            if self.lower {
+                self.y = WEAPON_BOTTOM;
+            }
+            return;
+        };
+        if self.tics < 0 {
+            return;
+        }
+        self.tics -= 1;
+        if self.tics <= 0 {
+            match transition(states, frame) {
+                Transition::To(next) => self.enter(tic, Some(next)),
+                Transition::Stop => self.enter(tic, None),
+                Transition::Stay => self.tics = -1,
+            }
+        }
+    }
+
+    /// Moves to `frame` and runs its action, then on through any zero tic frames
+    fn enter(&mut self, tic: &mut Tic, mut frame: Option<usize>) {
+        let states = tic.states;
+        for _ in 0..MAX_CHAINED_FRAMES {
+            self.frame = frame;
+            self.ready = None;
+            let Some(index) = frame else {
+                return;
+            };
+            let Some(state) = states.frames.get(index) else {
+                self.frame = None;
+                return;
+            };
+            self.tics = state.tics;
+            if let Some(action) = &state.action {
+                tic.actions.push(action.clone());
+                if let Some(jump) = self.run(tic, index, action) {
+                    frame = jump;
+                    continue;
+                }
+            }
+            if state.tics != 0 {
+                return;
+            }
+            frame = match transition(states, index) {
+                Transition::To(next) => Some(next),
+                Transition::Stop => None,
+                Transition::Stay => {
+                    self.tics = -1;
+                    return;
+                }
+            };
+        }
+        warn!(
+            "{}: more than {MAX_CHAINED_FRAMES} zero tic frames in a row",
+            tic.def.name
+        );
+    }
+
+    /// Runs a frame's action, `Some` when it jumps somewhere instead of carrying on
+    fn run(&mut self, tic: &mut Tic, index: usize, action: &StateAction) -> Option<Option<usize>> {
+        let mut rng = rand::thread_rng();
+        let states = tic.states;
+        let target = |arg: usize| jump_target(states, index, action, arg);
+        match action.name.as_str() {
+            "a_weaponready" => {
+                let flags = action.arg(0).unwrap_or_default().to_lowercase();
+                self.ready = Some(ReadyFlags {
+                    fire: !flags.contains("wrf_nofire"),
+                    reload: flags.contains("wrf_allowreload"),
+                    switch: !flags.contains("wrf_noswitch"),
+                    user1: flags.contains("wrf_allowuser1"),
+                });
+                self.y = WEAPON_TOP;
+                None
+            }
+            "a_raise" => {
+                self.y -= action.arg_f32(0).unwrap_or(RAISE_SPEED);
+                (self.y <= WEAPON_TOP).then(|| {
+                    self.y = WEAPON_TOP;
+                    states.label("ready")
+                })
+            }
+            "a_lower" => {
+                self.y += action.arg_f32(0).unwrap_or(RAISE_SPEED);
+                (self.y >= WEAPON_BOTTOM).then(|| {
+                    self.y = WEAPON_BOTTOM;
+                    None
+                })
+            }
+            "a_refire" => {
+                let semi = tic.mode.trigger == TriggerMode::Semi;
+                if tic.fire && !(semi && self.attack_down) {
+                    self.refire += 1;
+                    self.attack_down = true;
+                    let label = action.arg_str(0).unwrap_or("hold");
+                    Some(
+                        states
+                            .label(label)
+                            .or(states.label("hold"))
+                            .or(states.label("fire")),
+                    )
+                } else {
+                    self.refire = 0;
+                    None
+                }
+            }
+            "a_jump" => {
+                let chance = action.arg_f32(0).unwrap_or(0.0);
+                let targets = (1..action.args.len())
+                    .filter_map(target)
+                    .collect::<Vec<_>>();
+                (rng.gen_range(0.0..256.0) < chance)
+                    .then(|| targets.choose(&mut rng).copied())
+                    .flatten()
+                    .map(Some)
+            }
+            "a_jumpif" => {
+                // expressions aren't evaluated, only constant conditions
+                let condition = action.arg(0).unwrap_or_default().to_lowercase();
+                matches!(condition.as_str(), "1" | "true")
+                    .then(|| target(1))
+                    .flatten()
+                    .map(Some)
+            }
+            "a_jumpifinventory" => {
+                let item = action.arg_str(0)?;
+                let amount = action.arg_f32(1).unwrap_or(0.0) as u32;
+                let count = tic.inventory.count(item);
+                // an amount of 0 means the item is full
+                let jumps = match amount {
+                    0 => states
+                        .item_limits
+                        .get(&item.to_lowercase())
+                        .is_some_and(|limit| count >= *limit),
+                    _ => count >= amount,
+                };
+                jumps.then(|| target(2)).flatten().map(Some)
+            }
+            "a_giveinventory" => {
+                let item = action.arg_str(0)?;
+                let amount = action.arg_f32(1).map_or(1, |amount| amount.max(1.0) as u32);
+                let limit = tic.states.item_limits.get(&item.to_lowercase()).copied();
+                tic.inventory.give(item, amount, limit);
+                None
+            }
+            "a_takeinventory" => {
+                let item = action.arg_str(0)?;
+                // an amount of 0 takes them all
+                let amount = match action.arg_f32(1).unwrap_or(0.0) as u32 {
+                    0 => u32::MAX,
+                    amount => amount,
+                };
+                tic.inventory.take(item, amount);
+                None
+            }
+            "a_firebullets" => {
+                self.fire_bullets(tic, action);
+                None
+            }
+            "a_fireprojectile" | "a_firecustommissile" => {
+                self.fire_projectile(tic, action);
+                None
+            }
+            "a_setpitch" => {
+                // doom pitch looks down, relative to the current pitch when written `pitch+x`
+                let arg = action.arg(0).unwrap_or_default().replace(' ', "");
+                let arg = arg.to_lowercase();
+                let limit = FRAC_PI_2 - ANGLE_EPSILON;
+                if let Some(delta) = arg
+                    .strip_prefix("pitch")
+                    .and_then(|d| d.parse::<f32>().ok())
+                {
+                    tic.input.pitch = (tic.input.pitch - delta.to_radians()).clamp(-limit, limit);
+                } else if let Ok(pitch) = arg.parse::<f32>() {
+                    tic.input.pitch = (-pitch.to_radians()).clamp(-limit, limit);
+                }
+                None
+            }
+            _ => {
+                if let Some(sound) = sound_name(action)
+                    .and_then(|sound| tic.states.sounds.get(&sound.to_lowercase()))
+                    .and_then(|paths| paths.choose(&mut rng))
+                {
+                    tic.sounds.push(sound.clone());
+                }
+                None
+            }
+        }
+    }
+
+    /// `A_FireBullets(spread_h, spread_v, count, damage, puff, flags)`, queued for the fire path
+    fn fire_bullets(&mut self, tic: &mut Tic, action: &StateAction) {
+        let count = action.arg_f32(2).unwrap_or(1.0) as i32;
+        // flags default to FBF_USEAMMO
+        let use_ammo = action.arg(5).is_none_or(|flags| {
+            flags.parse::<u32>().map_or_else(
+                |_| flags.to_lowercase().contains("fbf_useammo"),
+                |flags| flags & 1 != 0,
+            )
+        });
+        if let Some(ammo) = tic.states.primary_ammo.as_ref().filter(|_| use_ammo) {
+            tic.inventory.take(ammo, tic.def.ammo_use);
+        }
+        // a lone bullet is accurate on the first shot and a count of 0 always is,
+        // -1 is a lone bullet that always spreads
+        let accurate = count == 0 || (count == 1 && self.refire == 0);
+        let spread = (
+            action.arg_f32(0).map_or(tic.mode.spread.0, f32::abs),
+            action.arg_f32(1).map_or(tic.mode.spread.1, f32::abs),
+        );
+        self.shots.push(FireMode {
+            pellets: count.max(1) as u32,
+            spread: if accurate { (0.0, 0.0) } else { spread },
+            damage: action.arg_f32(3).unwrap_or(tic.mode.damage),
+            ..tic.mode.clone()
+        });
+    }
+
+    /// `A_FireProjectile(missile, angle, useammo, ...)`, queued for the fire path like bullets.
+    /// `A_FireCustomMissile` takes the same arguments, its harmless effects were left out on import
+    fn fire_projectile(&mut self, tic: &mut Tic, action: &StateAction) {
+        let Some(missile) = action
+            .arg_str(0)
+            .and_then(|name| tic.states.missiles.get(&name.to_lowercase()))
+            .copied()
+        else {
+            return;
+        };
+        // useammo defaults to true
+        let use_ammo = action
+            .arg(2)
+            .is_none_or(|arg| !matches!(arg.to_lowercase().as_str(), "0" | "false"));
+        if let Some(ammo) = tic.states.primary_ammo.as_ref().filter(|_| use_ammo) {
+            tic.inventory.take(ammo, tic.def.ammo_use);
+        }
+        self.shots.push(FireMode {
+            pellets: 1,
+            spread: (0.0, 0.0),
+            damage: missile.damage,
+            damage_type: missile.damage_type,
+            shot: ShotKind::Projectile {
+                speed: missile.speed,
+            },
+            ..tic.mode.clone()
+        });
+    }
+}
+
+fn transition(states: &WeaponStates, index: usize) -> Transition {
+    match states.frames[index].next {
+        StateNext::Next if index + 1 < states.frames.len() => Transition::To(index + 1),
+        StateNext::Next | StateNext::Stop => Transition::Stop,
+        StateNext::Jump(target) => Transition::To(target),
+        StateNext::Wait => Transition::Stay,
+    }
+}
+
+/// A jump action's label or frame offset argument
+fn jump_target(
+    states: &WeaponStates,
+    index: usize,
+    action: &StateAction,
+    arg: usize,
+) -> Option<usize> {
+    if let Some(label) = action.arg_str(arg) {
+        let label = label
+            .get(..7)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("super::"))
+            .map_or(label, |_| &label[7..]);
+        return states.label(label);
+    }
+    let offset = action.arg(arg)?.parse::<usize>().ok()?;
+    Some(index + offset).filter(|target| *target < states.frames.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use avian3d::prelude::SpatialQueryPipeline;
+    use bevy::asset::AssetPlugin;
+
+    use super::*;
+    use crate::{
+        FireBallistic, LaunchProjectile, RenderPlayer, ShotHit, SpawnLight, Weapon, shoot_bullet,
+    };
+
+    #[test]
+    fn weapons_without_states_fire_without_an_inventory() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<WeaponDef>()
+            .init_resource::<SpatialQueryPipeline>()
+            .add_event::<WeaponStateAction>()
+            .add_event::<ShotHit>()
+            .add_event::<LaunchProjectile>()
+            .add_event::<FireBallistic>()
+            .add_event::<SpawnLight>()
+            .add_systems(Update, (step_weapon_states, shoot_bullet).chain());
+
+        let def = ron::from_str::<WeaponDef>(
+            "(
+                name: \"Launcher\",
+                fire_modes: [(
+                    trigger: Auto,
+                    cooldown: 0.0,
+                    damage: 10.0,
+                    shot: Projectile(speed: 20.0),
+                )],
+            )",
+        )
+        .unwrap();
+        let def = app.world_mut().resource_mut::<Assets<WeaponDef>>().add(def);
+        let player = app
+            .world_mut()
+            .spawn((
+                Weapon::new(def),
+                GoldenControllerInput {
+                    fire: true,
+                    ..default()
+                },
+            ))
+            .id();
+        app.world_mut().spawn((
+            Camera3d::default(),
+            RenderPlayer {
+                logical_entity: player,
+            },
+        ));
+
+        let mut launched = 0;
+        for _ in 0..64 {
+            app.update();
+            launched += app
+                .world_mut()
+                .resource_mut::<Events<LaunchProjectile>>()
+                .drain()
+                .count();
+        }
+        let machine = app.world().get::<WeaponStateMachine>(player).unwrap();
+        assert_eq!(machine.y, WEAPON_TOP);
+        assert!(launched > 0);
+    }
+}